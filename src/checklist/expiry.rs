@@ -0,0 +1,127 @@
+use super::model::{Checklist, ChecklistItem};
+use log::debug;
+use std::str::FromStr;
+use time::{Duration, OffsetDateTime};
+
+/// When a resolution stops counting, from `expires = '24h'` and `reset = 'daily'`
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct Expiry {
+    /// How long a resolution lasts
+    pub expires: Option<Duration>,
+    pub reset: Option<Reset>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Reset {
+    /// Resolutions from before today, in the local time zone, are dropped
+    Daily,
+    /// Resolutions saved on another commit than `HEAD` are dropped
+    OnNewCommit,
+}
+
+impl FromStr for Reset {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "daily" => Ok(Reset::Daily),
+            "on-new-commit" => Ok(Reset::OnNewCommit),
+            _ => Err(format!(
+                "unknown reset {input:?}, expected daily or on-new-commit"
+            )),
+        }
+    }
+}
+
+/// The current time in the local time zone, in UTC when the offset can't be told, like while
+/// other threads run
+pub fn local_now() -> OffsetDateTime {
+    OffsetDateTime::now_local().unwrap_or_else(|error| {
+        debug!("[local_now] {error}, using UTC");
+        OffsetDateTime::now_utc()
+    })
+}
+
+/// Parses a duration like `30m`, `24h` or `7d`
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let error =
+        || format!("invalid duration {input:?}, expected a number followed by s, m, h, d or w");
+    let input = input.trim();
+    let unit_start = input
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(error)?;
+    let amount: i64 = input[..unit_start].parse().map_err(|_| error())?;
+    match &input[unit_start..] {
+        "s" => Ok(Duration::seconds(amount)),
+        "m" => Ok(Duration::minutes(amount)),
+        "h" => Ok(Duration::hours(amount)),
+        "d" => Ok(Duration::days(amount)),
+        "w" => Ok(Duration::weeks(amount)),
+        _ => Err(error()),
+    }
+}
+
+impl Expiry {
+    /// The rules of an item, falling back to those of its checklist
+    fn or(self, checklist: Expiry) -> Expiry {
+        Expiry {
+            expires: self.expires.or(checklist.expires),
+            reset: self.reset.or(checklist.reset),
+        }
+    }
+
+    fn has_expired(
+        &self,
+        resolved_at: OffsetDateTime,
+        now: OffsetDateTime,
+        new_commit: bool,
+    ) -> bool {
+        let too_old = self
+            .expires
+            .is_some_and(|expires| now - resolved_at >= expires);
+        let was_reset = match self.reset {
+            // Days start at midnight where `now` is, not in UTC
+            Some(Reset::Daily) => resolved_at.to_offset(now.offset()).date() < now.date(),
+            Some(Reset::OnNewCommit) => new_commit,
+            None => false,
+        };
+        too_old || was_reset
+    }
+}
+
+impl Checklist {
+    /// Makes `reset` the rule of the checklist and of every item, whatever they set themselves
+    pub fn set_reset(&mut self, reset: Reset) {
+        self.expiry.reset = Some(reset);
+        self.walk_mut(|_, item: &mut ChecklistItem| item.expiry.reset = Some(reset));
+    }
+
+    /// Unresolves the items whose resolution has expired, as of `now` and the commit at `head`.
+    /// Only resolutions that recorded when they happened can expire.
+    /// Returns the text of the expired items
+    pub fn expire_resolutions(&mut self, head: Option<&str>, now: OffsetDateTime) -> Vec<String> {
+        let checklist_expiry = self.expiry;
+        let new_commit = self.commit.as_deref() != head;
+        let mut expired = Vec::new();
+        self.walk_mut(|_, item: &mut ChecklistItem| {
+            let Some(resolved_at) = item.resolved_at.filter(|_| item.resolved) else {
+                return;
+            };
+            if item
+                .expiry
+                .or(checklist_expiry)
+                .has_expired(resolved_at, now, new_commit)
+            {
+                debug!(
+                    "[expire_resolutions] Resolution of {:?} from {resolved_at} has expired",
+                    item.text
+                );
+                item.resolved = false;
+                item.resolved_at = None;
+                item.resolved_by = None;
+                expired.push(item.text.clone());
+            }
+        });
+        expired
+    }
+}
@@ -0,0 +1,108 @@
+use super::model::{Checklist, now_utc};
+use crate::error::{Format, KneeboardError, Result};
+use serde::{Deserialize, Serialize};
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
+
+/// Every recorded run of a checklist, oldest first
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct History {
+    #[serde(default)]
+    pub runs: Vec<RunRecord>,
+}
+
+/// A completed run of a checklist
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct RunRecord {
+    #[serde(with = "time::serde::rfc3339")]
+    pub timestamp: OffsetDateTime,
+    pub user: String,
+    pub checklist: String,
+    pub unresolved: usize,
+    /// Items whose state changed during the run
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub changes: Vec<ItemChange>,
+    /// Text of the items skipped out of order during the run
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ItemChange {
+    pub text: String,
+    pub resolved: bool,
+}
+
+impl RunRecord {
+    /// Records a run that took the checklist from `before` to `after`
+    pub fn new(before: &Checklist, after: &Checklist, user: String) -> RunRecord {
+        RunRecord {
+            timestamp: now_utc(),
+            user,
+            checklist: after.name.clone(),
+            unresolved: after.get_count_unresolved(),
+            changes: after.changes_since(before),
+            skipped: after.skips_since(before),
+        }
+    }
+
+    /// The record as a `[[runs]]` entry that can be appended to a history file
+    pub fn to_toml(&self) -> Result<String> {
+        let entry = History {
+            runs: vec![self.clone()],
+        };
+        toml::to_string_pretty(&entry).map_err(|error| KneeboardError::Serialize {
+            format: Format::Toml,
+            source: Box::new(error),
+        })
+    }
+}
+
+impl RunRecord {
+    pub fn formatted_timestamp(&self) -> String {
+        self.timestamp.format(&Rfc3339).unwrap_or_default()
+    }
+}
+
+impl History {
+    pub fn from_toml(input_string: String) -> Result<History> {
+        toml::from_str::<History>(&input_string)
+            .map_err(|error| KneeboardError::toml(&input_string, error))
+    }
+}
+
+impl Checklist {
+    /// Items whose resolved state differs from the same item in `before`,
+    /// items are paired up in the order of [`Checklist::walk`]
+    pub fn changes_since(&self, before: &Checklist) -> Vec<ItemChange> {
+        let before_items = before.walk();
+        self.walk()
+            .into_iter()
+            .enumerate()
+            .filter(|(index, (_, item))| {
+                before_items
+                    .get(*index)
+                    .is_none_or(|(_, before_item)| before_item.resolved != item.resolved)
+            })
+            .map(|(_, (_, item))| ItemChange {
+                text: item.text.clone(),
+                resolved: item.resolved,
+            })
+            .collect()
+    }
+
+    /// Text of the items skipped since `before`, paired up like [`Checklist::changes_since`]
+    pub fn skips_since(&self, before: &Checklist) -> Vec<String> {
+        let before_items = before.walk();
+        self.walk()
+            .into_iter()
+            .enumerate()
+            .filter(|(index, (_, item))| {
+                item.skipped_at.is_some()
+                    && before_items
+                        .get(*index)
+                        .is_none_or(|(_, before_item)| before_item.skipped_at != item.skipped_at)
+            })
+            .map(|(_, (_, item))| item.text.clone())
+            .collect()
+    }
+}
@@ -0,0 +1,174 @@
+use super::model::{
+    ParsedChecklist, parse_checklist_expiry, parse_checklist_key, parse_checklist_mode,
+    parse_checklist_name, parse_markdown,
+};
+use crate::error::Location;
+use log::debug;
+use std::collections::HashMap;
+use std::fmt;
+
+/// A problem found in a checklist file, pointing at where it happened
+#[derive(Debug, PartialEq)]
+pub struct Diagnostic {
+    pub location: Location,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}",
+            self.location.line, self.location.column, self.message
+        )
+    }
+}
+
+/// Checks the checklists in the Markdown against SPEC.md, diagnostics are in document order
+pub fn lint(markdown_input: &str) -> Vec<Diagnostic> {
+    let parsed_checklists = parse_markdown(markdown_input);
+    let mut diagnostics = Vec::<(usize, String)>::new();
+
+    if parsed_checklists.is_empty() {
+        diagnostics.push((0, String::from("no checklist found")));
+    }
+
+    for (index, parsed) in parsed_checklists.iter().enumerate() {
+        let name = &parsed.checklist.name;
+        let mut delimiters = vec![&parsed.start];
+        delimiters.extend(&parsed.end);
+        for delimiter in delimiters {
+            if !is_valid_delimiter(&markdown_input[delimiter.clone()]) {
+                diagnostics.push((
+                    delimiter.start,
+                    String::from(
+                        "invalid checklist delimiter, expected <!-- checklist = 'name' --> or <!-- checklist -->",
+                    ),
+                ));
+            }
+        }
+
+        let delimiter = &markdown_input[parsed.start.clone()];
+        if let Err(message) = parse_checklist_mode(delimiter) {
+            diagnostics.push((parsed.start.start, message));
+        }
+        if let Err(message) = parse_checklist_expiry(delimiter) {
+            diagnostics.push((parsed.start.start, message));
+        }
+        if let Err(message) = parse_checklist_key(delimiter) {
+            diagnostics.push((parsed.start.start, message));
+        }
+
+        if parsed.checklist.items.is_empty() {
+            let message = unbalanced_delimiter(markdown_input, &parsed_checklists[..index], parsed)
+                .unwrap_or_else(|| format!("checklist {name:?} has no items"));
+            diagnostics.push((parsed.start.start, message));
+        }
+
+        lint_items(markdown_input, parsed, &mut diagnostics);
+    }
+
+    diagnostics.sort_by_key(|(offset, _)| *offset);
+    diagnostics
+        .into_iter()
+        .map(|(offset, message)| {
+            let diagnostic = Diagnostic {
+                location: Location::from_offset(markdown_input, offset),
+                message,
+            };
+            debug!("[lint] {diagnostic}");
+            diagnostic
+        })
+        .collect()
+}
+
+fn lint_items(
+    markdown_input: &str,
+    parsed: &ParsedChecklist,
+    diagnostics: &mut Vec<(usize, String)>,
+) {
+    let mut seen = HashMap::<&str, usize>::new();
+    let mut seen_ids = HashMap::<&str, usize>::new();
+    for ((_, item), span) in parsed.checklist.walk().into_iter().zip(&parsed.spans) {
+        let item_start = span.start(markdown_input);
+        if let Some(first) = seen.get(item.text.as_str()) {
+            diagnostics.push((
+                item_start,
+                format!(
+                    "duplicate item {:?}, first defined on line {}",
+                    item.text,
+                    Location::from_offset(markdown_input, *first).line
+                ),
+            ));
+        } else {
+            seen.insert(&item.text, item_start);
+        }
+
+        if let Some(id) = &item.id {
+            if let Some(first) = seen_ids.get(id.as_str()) {
+                diagnostics.push((
+                    item_start,
+                    format!(
+                        "duplicate id {id:?}, first defined on line {}",
+                        Location::from_offset(markdown_input, *first).line
+                    ),
+                ));
+            } else {
+                seen_ids.insert(id, item_start);
+            }
+        }
+
+        if span.marker.is_none() && is_malformed_checkbox(&item.text) {
+            diagnostics.push((
+                item_start,
+                format!("malformed checkbox in {:?}, expected [ ] or [x]", item.text),
+            ));
+        }
+    }
+}
+
+/// A delimiter that started an empty checklist because nothing it could close was open, like
+/// the second `a` of interleaved `a`, `b`, `a` delimiters or a stray bare delimiter.
+/// `None` when it did start a checklist, one left without items. Leaving the end delimiter
+/// out is fine, the checklist runs to the next one
+fn unbalanced_delimiter(
+    markdown_input: &str,
+    earlier: &[ParsedChecklist],
+    parsed: &ParsedChecklist,
+) -> Option<String> {
+    if parsed.end.is_some() {
+        return None;
+    }
+    let delimiter = &markdown_input[parsed.start.clone()];
+    match parse_checklist_name(delimiter) {
+        Some(name) => earlier
+            .iter()
+            .any(|checklist| checklist.end.is_none() && checklist.checklist.name == name)
+            .then(|| format!("delimiter closes checklist {name:?}, which isn't open")),
+        None if is_valid_delimiter(delimiter) => Some(String::from(
+            "delimiter closes a checklist, but none is open",
+        )),
+        None => None,
+    }
+}
+
+/// A delimiter is valid when it is a bare `checklist` or has a TOML `checklist` name
+fn is_valid_delimiter(delimiter: &str) -> bool {
+    let content = delimiter
+        .trim()
+        .trim_start_matches("<!--")
+        .trim_end_matches("-->")
+        .trim();
+    content == "checklist" || parse_checklist_name(delimiter).is_some()
+}
+
+/// Text that starts like a checkbox but wasn't parsed as one, such as `[]` or `[ x]`
+fn is_malformed_checkbox(text: &str) -> bool {
+    let Some(rest) = text.trim_start().strip_prefix('[') else {
+        return false;
+    };
+    match rest.find(']') {
+        Some(end) => rest[..end].chars().all(|c| matches!(c, ' ' | 'x' | 'X')),
+        None => false,
+    }
+}
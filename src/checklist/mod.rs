@@ -1,6 +1,18 @@
+mod expiry;
+mod history;
+mod lint;
 mod model;
+mod report;
+mod select;
+mod trailers;
+mod verify;
 
-pub use model::{Checklist, ChecklistItem};
+pub use expiry::{Reset, local_now};
+pub use history::{History, RunRecord};
+pub use lint::lint;
+pub use model::{Checklist, ChecklistItem, ChecklistMode, SavedScope};
+pub use report::ReportTarget;
+pub use trailers::{CHECKLIST_TRAILER, ChecklistTrailer, SKIPPED_TRAILER};
 
 #[cfg(test)]
 mod tests;
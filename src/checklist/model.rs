@@ -1,192 +1,896 @@
-use log::{debug, warn};
+use super::expiry::{Expiry, parse_duration};
+use crate::error::{Format, KneeboardError, Location, Result};
+use log::{debug, info, warn};
 use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
 use serde::{Deserialize, Serialize};
-use std::error;
+use std::fs;
+use std::ops::Range;
+use std::str::FromStr;
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
 use toml::Table;
 
-type Result<T> = std::result::Result<T, Box<dyn error::Error>>;
-
 #[derive(Clone, Debug, Deserialize, Serialize, Default)]
 pub struct Checklist {
     pub items: Vec<ChecklistItem>,
     pub name: String,
+    /// Order the items have to be worked in, given by `mode = '...'` in the delimiter
+    #[serde(default, skip_serializing_if = "ChecklistMode::is_free")]
+    pub mode: ChecklistMode,
+    /// When resolutions expire, given by `expires` and `reset` in the delimiter
+    #[serde(skip)]
+    pub expiry: Expiry,
+    /// What progress is saved under instead of the path and name, given by `key = '...'` in the delimiter
+    #[serde(skip)]
+    pub key: Option<String>,
+    /// The git `HEAD` the progress was saved on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    /// The branch or worktree the progress was saved for, `None` when it is shared
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<SavedScope>,
+}
+
+/// The branch or worktree saved progress belongs to
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct SavedScope {
+    /// The git directory shared by every worktree of the repository
+    pub repository: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub worktree: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ChecklistMode {
+    /// Items can be resolved in any order
+    #[default]
+    Free,
+    /// Items are resolved top to bottom, skipping one needs an explicit override
+    Sequential,
+}
+
+impl ChecklistMode {
+    fn is_free(&self) -> bool {
+        *self == ChecklistMode::Free
+    }
 }
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize, Default)]
 pub struct ChecklistItem {
+    /// The item, or the challenge of a `challenge :: response` item
     pub text: String,
+    /// Expected response given after `::`, e.g. `SET 15` in `Flaps :: SET 15`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<String>,
+    /// Stable identifier given by `{#id}` or `<!-- id: id -->`, used to match saved progress
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
     pub optional: bool,
     pub resolved: bool,
+    /// When the item was resolved, cleared once it is unresolved again
+    #[serde(
+        default,
+        with = "time::serde::rfc3339::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub resolved_at: Option<OffsetDateTime>,
+    /// Who resolved the item
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_by: Option<String>,
+    /// When the item was skipped out of order in a sequential checklist, cleared once it is resolved
+    #[serde(
+        default,
+        with = "time::serde::rfc3339::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub skipped_at: Option<OffsetDateTime>,
+    /// Who skipped the item
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skipped_by: Option<String>,
+    /// Command given by `<!-- run: command -->` whose exit status resolves the item
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run: Option<String>,
+    /// Whether the command ran in this run and failed, the item then waits on it passing
+    /// instead of being resolved by hand
+    #[serde(skip)]
+    pub run_failed: bool,
+    /// When the resolution of this item expires, given by `<!-- expires: 24h -->` and `<!-- reset: daily -->`
+    #[serde(skip)]
+    pub expiry: Expiry,
+    /// Free text recorded against the item during a run, like why it was skipped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<ChecklistItem>,
+}
+
+impl ChecklistItem {
+    /// An item is only resolved once it is ticked and all of its required children are resolved
+    pub fn is_resolved(&self) -> bool {
+        self.resolved
+            && self
+                .children
+                .iter()
+                .all(|child| child.optional || child.is_resolved())
+    }
+
+    /// Whether `selector` is the text or the id of the item
+    pub fn matches(&self, selector: &str) -> bool {
+        self.text == selector || self.id.as_deref() == Some(selector)
+    }
+
+    /// Resolves or unresolves the item, stamping who resolved it and when
+    pub fn set_resolved(&mut self, resolved: bool, user: &str) {
+        if resolved && !self.resolved {
+            self.resolved_at = Some(now_utc());
+            self.resolved_by = Some(user.to_string());
+            self.skipped_at = None;
+            self.skipped_by = None;
+        } else if !resolved {
+            self.resolved_at = None;
+            self.resolved_by = None;
+        }
+        self.resolved = resolved;
+    }
+
+    /// Records that `user` skipped the item out of order
+    pub fn skip(&mut self, user: &str) {
+        self.skipped_at = Some(now_utc());
+        self.skipped_by = Some(user.to_string());
+    }
+
+    /// Who resolved the item and when, if that was recorded
+    pub fn resolution(&self) -> Option<String> {
+        let at = self
+            .resolved_at
+            .and_then(|resolved_at| resolved_at.format(&Rfc3339).ok());
+        match (&self.resolved_by, at) {
+            (Some(by), Some(at)) => Some(format!("{by} at {at}")),
+            (Some(by), None) => Some(by.clone()),
+            (None, Some(at)) => Some(at),
+            (None, None) => None,
+        }
+    }
+
+    /// Carries the progress recorded in a saved item over to this one
+    fn merge_progress(&mut self, saved: &ChecklistItem) {
+        self.resolved = self.resolved || saved.resolved;
+        if saved.resolved && self.resolved_at.is_none() && self.resolved_by.is_none() {
+            self.resolved_at = saved.resolved_at;
+            self.resolved_by.clone_from(&saved.resolved_by);
+        }
+        if !self.resolved && self.skipped_at.is_none() {
+            self.skipped_at = saved.skipped_at;
+            self.skipped_by.clone_from(&saved.skipped_by);
+        }
+        if self.note.is_none() {
+            self.note.clone_from(&saved.note);
+        }
+    }
+
+    fn count_unresolved(&self) -> usize {
+        let own = usize::from(!self.optional && !self.is_resolved());
+        own + self
+            .children
+            .iter()
+            .map(ChecklistItem::count_unresolved)
+            .sum::<usize>()
+    }
+}
+
+impl FromStr for ChecklistMode {
+    type Err = String;
+
+    fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
+        match input {
+            "free" => Ok(ChecklistMode::Free),
+            "sequential" => Ok(ChecklistMode::Sequential),
+            _ => Err(format!(
+                "unknown checklist mode {input:?}, expected free or sequential"
+            )),
+        }
+    }
 }
 
 impl Checklist {
+    /// Parses the first checklist found in the Markdown
     pub fn from_markdown(markdown_input: String) -> Result<Checklist> {
-        let mut checklist = Checklist::default();
-        let mut options = Options::empty();
-        options.insert(Options::ENABLE_TASKLISTS);
-        let parser = Parser::new_ext(&markdown_input, options);
-        let (mut is_list, mut nested_list_level, mut is_checklist, mut is_list_item) =
-            (false, 0, false, false);
-        let mut checklist_item = ChecklistItem::default();
-        for event in parser {
-            match &event {
-                Event::Start(tag) => match *tag {
-                    Tag::List(_) => {
-                        debug!("[extract_checklist][event:start] List found");
-                        if is_list {
-                            nested_list_level += 1;
-                        }
+        let checklists = Checklist::from_markdown_all(markdown_input)?;
+        if checklists.len() > 1 {
+            info!(
+                "[from_markdown] Found {} checklists using {:?}",
+                checklists.len(),
+                checklists[0].name
+            );
+        }
+        Ok(checklists.into_iter().next().unwrap_or_default())
+    }
 
-                        is_list = true
-                    }
-                    Tag::Item => {
-                        if is_list && is_checklist && is_list_item {
-                            debug!(
-                                "[extract_checklist][event:start] Adding ChecklistItem: {checklist_item:?}"
-                            );
-                            checklist.items.push(checklist_item.clone());
+    /// Parses the checklist called `name`, or called `name` by older versions
+    pub fn from_markdown_named(markdown_input: String, name: &str) -> Result<Checklist> {
+        let mut checklists = Checklist::from_markdown_all(markdown_input)?;
+        let names: Vec<String> = checklists.iter().map(|c| c.name.clone()).collect();
+        checklists
+            .iter()
+            .position(|checklist| checklist.name == name)
+            .or_else(|| {
+                checklists
+                    .iter()
+                    .position(|checklist| checklist.legacy_name() == name)
+            })
+            .map(|index| checklists.swap_remove(index))
+            .ok_or_else(|| {
+                KneeboardError::validation(format!(
+                    "no checklist named {name:?} found, available checklists are {names:?}"
+                ))
+            })
+    }
 
-                            checklist_item = ChecklistItem::default();
-                        }
-                        is_list_item = true;
-                    }
-                    _ => (),
-                },
-                Event::Text(string) => {
-                    if is_list && is_checklist && is_list_item {
-                        debug!(
-                            "[extract_checklist][event:text] ChecklistItem Found text: {string:?}"
-                        );
-                        checklist_item.text.push_str(string);
-                    }
-                }
-                Event::TaskListMarker(status) => {
-                    if is_list && is_checklist && is_list_item {
-                        debug!(
-                            "[extract_checklist][event:tasklistmarker] ChecklistItem Found TaskListMarker: {status:?}"
-                        );
+    /// Reads the checklist file, picking the checklist called `name` or the first one
+    pub fn load(checklist_path: &str, name: Option<&str>) -> Result<Checklist> {
+        let file_contents = fs::read_to_string(checklist_path)
+            .map_err(|error| KneeboardError::io(checklist_path, error))?;
+        match name {
+            Some(name) => Checklist::from_markdown_named(file_contents, name),
+            None => Checklist::from_markdown(file_contents),
+        }
+    }
 
-                        checklist_item.resolved = *status;
-                    }
-                }
-                Event::End(tag) => match *tag {
-                    TagEnd::List(_) => {
-                        if nested_list_level > 0 {
-                            nested_list_level -= 1;
-                        } else {
-                            is_list = false;
-                        }
-                    }
-                    TagEnd::Item => {
-                        is_list_item = false;
-                        if is_list && is_checklist {
-                            debug!(
-                                "[extract_checklist][event:end] Adding ChecklistItem: {checklist_item:?}"
-                            );
-                            if !checklist_item.text.is_empty() {
-                                checklist.items.push(checklist_item.clone());
-                            }
+    /// Parses every checklist found in the Markdown, in document order
+    pub fn from_markdown_all(markdown_input: String) -> Result<Vec<Checklist>> {
+        let parsed_checklists = parse_markdown(&markdown_input);
+        // Points at the first checklist when they all turn out to have no items
+        let first_delimiter = parsed_checklists.first().map(|parsed| parsed.start.start);
+        let mut checklists: Vec<Checklist> = parsed_checklists
+            .into_iter()
+            .map(|parsed| parsed.checklist)
+            .collect();
 
-                            checklist_item = ChecklistItem::default();
-                        }
-                    }
-                    _ => (),
-                },
-                Event::Html(string) => {
-                    if string.contains("checklist") && string.contains("<!--") {
-                        checklist.name = extract_checklist_name(string.to_string());
-                        if is_checklist {
-                            debug!("[extract_checklist][event:html] Found end of checklist");
-                            is_list = false;
-                            is_checklist = false;
-                        } else {
-                            debug!("[extract_checklist][event:html] Found start of checklist");
-                            is_checklist = true;
-                        }
-                    }
-                }
-                _ => (),
-            };
-        }
+        checklists.retain(|checklist| {
+            if checklist.items.is_empty() {
+                warn!(
+                    "[extract_checklist] Skipping checklist {:?} as it has no items",
+                    checklist.name
+                );
+            }
+            !checklist.items.is_empty()
+        });
 
-        if checklist.items.is_empty() {
+        if checklists.is_empty() {
             warn!("[extract_checklist] Found No Checklist or and Items returning Empty Checklist");
-            return Err(
-                "[extract_checklist] Found No Checklist or and Items returning Empty Checklist"
-                    .into(),
-            );
+            return Err(KneeboardError::Parse {
+                format: Format::Markdown,
+                message: String::from("found no checklist with items"),
+                location: first_delimiter
+                    .map(|offset| Location::from_offset(&markdown_input, offset)),
+                source: None,
+            });
         }
-        for checklist_item in checklist.items.iter_mut() {
-            if checklist_item.text.contains("[OPTIONAL]") {
+
+        Ok(checklists)
+    }
+
+    /// Rewrites the task list markers of this checklist in the Markdown to match its items,
+    /// leaving every other byte of the document untouched
+    pub fn write_back(&self, markdown_input: &str) -> Result<String> {
+        let parsed = parse_markdown(markdown_input)
+            .into_iter()
+            .find(|parsed| parsed.checklist.name == self.name)
+            .ok_or_else(|| {
+                KneeboardError::validation(format!("no checklist named {:?} found", self.name))
+            })?;
+        let parsed_items = parsed.checklist.walk();
+        let items = self.walk();
+        let text = |items: &[(usize, &ChecklistItem)], index: usize| {
+            items.get(index).map(|(_, item)| item.text.clone())
+        };
+        if let Some(index) = (0..parsed_items.len().max(items.len()))
+            .find(|&index| text(&parsed_items, index) != text(&items, index))
+        {
+            // The first item that differs, or the end of the checklist when items are missing
+            let offset = parsed
+                .spans
+                .get(index)
+                .map(|span| span.start(markdown_input))
+                .or(parsed.end.as_ref().map(|end| end.start))
+                .unwrap_or(markdown_input.len());
+            return Err(KneeboardError::validation_at(
+                format!("checklist {:?} no longer matches the Markdown", self.name),
+                markdown_input,
+                offset,
+            ));
+        }
+
+        let mut output = markdown_input.to_string();
+        for ((_, item), span) in items.iter().zip(&parsed.spans) {
+            let Some(marker) = &span.marker else {
                 debug!(
-                    "[set_optionality] Setting {:?} to optional",
-                    checklist_item.text
+                    "[write_back] {:?} has no task list marker, skipping",
+                    item.text
                 );
-                checklist_item.optional = true;
+                continue;
+            };
+            // Markers are always `[?]` so the state is the single byte between the brackets
+            let state = marker.start + 1..marker.end - 1;
+            let is_checked = &markdown_input[state.clone()] != " ";
+            if is_checked != item.resolved {
+                debug!("[write_back] Setting {:?} to {}", item.text, item.resolved);
+                output.replace_range(state, if item.resolved { "x" } else { " " });
             }
         }
-
-        Ok(checklist)
+        Ok(output)
     }
 
     pub fn to_toml(&self) -> Result<String> {
-        match toml::to_string_pretty(self) {
-            Ok(s) => Ok(s),
-            Err(_) => Err("[to_toml] failed to generate toml".into()),
-        }
+        toml::to_string_pretty(self).map_err(|error| KneeboardError::Serialize {
+            format: Format::Toml,
+            source: Box::new(error),
+        })
     }
 
     pub fn from_toml(input_string: String) -> Result<Checklist> {
-        match toml::from_str::<Checklist>(&input_string) {
-            Ok(checklist) => Ok(checklist),
-            Err(_) => Err("[from_toml] failed parse ChecklistItems from TOML".into()),
-        }
+        toml::from_str::<Checklist>(&input_string)
+            .map_err(|error| KneeboardError::toml(&input_string, error))
+    }
+
+    /// The name older versions gave the checklist, they removed every `<`, `!`, `-` and `>`
+    /// from the delimiter name and all. Save files are still found by it
+    pub fn legacy_name(&self) -> String {
+        self.name.replace(&['<', '!', '-', '>'][..], "")
     }
 
     pub fn get_count_unresolved(&self) -> usize {
-        let mut count: usize = 0;
-        for checklist_item in &self.items {
-            if !&checklist_item.resolved && !&checklist_item.optional {
-                count += 1;
+        self.items.iter().map(ChecklistItem::count_unresolved).sum()
+    }
+
+    /// Walks every item depth first, yielding each item along with its nesting depth
+    pub fn walk(&self) -> Vec<(usize, &ChecklistItem)> {
+        fn visit<'a>(
+            items: &'a [ChecklistItem],
+            depth: usize,
+            walked: &mut Vec<(usize, &'a ChecklistItem)>,
+        ) {
+            for item in items {
+                walked.push((depth, item));
+                visit(&item.children, depth + 1, walked);
             }
         }
-        count
+        let mut walked = Vec::new();
+        visit(&self.items, 0, &mut walked);
+        walked
     }
 
-    pub fn merge_checklist(&mut self, other: &Checklist) {
-        for item in &mut self.items {
-            if let Some(other_item) = other.items.iter().find(|i| i.text == item.text) {
-                item.resolved = item.resolved || other_item.resolved;
+    /// Mutable counterpart of [`Checklist::walk`], visiting items in the same order
+    pub fn walk_mut(&mut self, mut f: impl FnMut(usize, &mut ChecklistItem)) {
+        fn visit(
+            items: &mut [ChecklistItem],
+            depth: usize,
+            f: &mut impl FnMut(usize, &mut ChecklistItem),
+        ) {
+            for item in items {
+                f(depth, item);
+                visit(&mut item.children, depth + 1, f);
             }
         }
+        visit(&mut self.items, 0, &mut f);
+    }
+
+    /// Stamps the items resolved since `before` with `user` and the current time,
+    /// and drops the stamps of unresolved items. Items are paired up in the order of [`Checklist::walk`]
+    pub fn stamp_resolutions(&mut self, before: &Checklist, user: &str) {
+        let was_resolved: Vec<bool> = before
+            .walk()
+            .iter()
+            .map(|(_, item)| item.resolved)
+            .collect();
+        let now = now_utc();
+        let mut index = 0;
+        self.walk_mut(|_, item| {
+            let stamped = item.resolved_at.is_some() || item.resolved_by.is_some();
+            if !item.resolved {
+                item.resolved_at = None;
+                item.resolved_by = None;
+            } else if !stamped && !was_resolved.get(index).copied().unwrap_or(false) {
+                item.resolved_at = Some(now);
+                item.resolved_by = Some(user.to_string());
+            }
+            index += 1;
+        });
+    }
+
+    /// Merges the progress of a saved checklist into this one.
+    ///
+    /// Items are matched by id, then by exact text, then by the most similar text so that
+    /// small edits to an item keep its progress. Texts with different numbers in them never
+    /// match by similarity and neither do items with more than one equally similar saved item
+    pub fn merge_checklist(&mut self, other: &Checklist) -> MergeReport {
+        self.commit.clone_from(&other.commit);
+        let other_items = other.walk();
+        let mut matched = vec![false; other_items.len()];
+        let mut unmatched_items = Vec::<usize>::new();
+
+        let mut index = 0;
+        self.walk_mut(|_, item| {
+            let position =
+                other_items
+                    .iter()
+                    .position(|(_, other_item)| match (&item.id, &other_item.id) {
+                        (Some(id), Some(other_id)) => id == other_id,
+                        (Some(_), None) | (None, None) => other_item.text == item.text,
+                        (None, Some(_)) => false,
+                    });
+            match position {
+                Some(position) => {
+                    item.merge_progress(other_items[position].1);
+                    matched[position] = true;
+                }
+                None => unmatched_items.push(index),
+            }
+            index += 1;
+        });
+
+        // Fall back to the most similar saved item that wasn't matched already
+        let mut report = MergeReport::default();
+        let mut index = 0;
+        self.walk_mut(|_, item| {
+            if unmatched_items.contains(&index) {
+                let candidates: Vec<(usize, f64)> = other_items
+                    .iter()
+                    .enumerate()
+                    .filter(|(position, (_, other_item))| {
+                        !matched[*position]
+                            && (item.id.is_none() || other_item.id.is_none())
+                            && numbers(&item.text) == numbers(&other_item.text)
+                    })
+                    .map(|(position, (_, other_item))| {
+                        (position, similarity(&item.text, &other_item.text))
+                    })
+                    .filter(|(_, score)| *score >= SIMILARITY_THRESHOLD)
+                    .collect();
+                let best = candidates
+                    .iter()
+                    .max_by(|(_, a), (_, b)| a.total_cmp(b))
+                    .copied();
+                let ambiguous = best.is_some_and(|(best_position, best_score)| {
+                    candidates
+                        .iter()
+                        .any(|(position, score)| *position != best_position && *score == best_score)
+                });
+                match best {
+                    Some((position, score)) if !ambiguous => {
+                        let other_item = other_items[position].1;
+                        info!(
+                            "[merge_checklist] Matched saved {:?} to {:?} with similarity {score:.2}",
+                            other_item.text, item.text
+                        );
+                        item.merge_progress(other_item);
+                        matched[position] = true;
+                        report
+                            .similar
+                            .push((other_item.text.clone(), item.text.clone()));
+                    }
+                    Some(_) => debug!(
+                        "[merge_checklist] {:?} is as similar to more than one saved item",
+                        item.text
+                    ),
+                    None => (),
+                }
+            }
+            index += 1;
+        });
+
+        report.unmatched = other_items
+            .iter()
+            .zip(matched)
+            .filter(|(_, matched)| !matched)
+            .map(|((_, other_item), _)| other_item.text.clone())
+            .collect();
+        report
+    }
+}
+
+/// What merging saved progress did besides matching items by id or exact text
+#[derive(Debug, Default, PartialEq)]
+pub struct MergeReport {
+    /// The saved and the current text of items matched by similar text
+    pub similar: Vec<(String, String)>,
+    /// The text of saved items that matched no item, their progress was dropped
+    pub unmatched: Vec<String>,
+}
+
+/// The current time to the second, as stored in save and history files
+pub(super) fn now_utc() -> OffsetDateTime {
+    let now = OffsetDateTime::now_utc();
+    now.replace_nanosecond(0).unwrap_or(now)
+}
+
+/// How similar two item texts must be for saved progress to carry over
+const SIMILARITY_THRESHOLD: f64 = 0.8;
+
+/// The numbers in an item text, `run migration 2` and `run migration 3` are different items
+fn numbers(text: &str) -> Vec<&str> {
+    text.split(|c: char| !c.is_ascii_digit())
+        .filter(|number| !number.is_empty())
+        .collect()
+}
+
+/// Similarity between two strings from 0 to 1, based on their Levenshtein distance
+fn similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != b_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    1.0 - previous[b.len()] as f64 / longest as f64
+}
+
+/// Byte ranges of a checklist item in the Markdown source
+#[derive(Debug, Clone)]
+pub(super) struct ItemSpan {
+    pub item: Range<usize>,
+    /// The `[ ]` or `[x]` task list marker, if the item has one
+    pub marker: Option<Range<usize>>,
+}
+
+impl ItemSpan {
+    /// Where the item starts in the Markdown, nested item ranges can start within their indentation
+    pub fn start(&self, markdown_input: &str) -> usize {
+        let item = &markdown_input[self.item.clone()];
+        self.item.start + item.len() - item.trim_start().len()
+    }
+}
+
+/// A checklist along with where it and its items sit in the Markdown source
+#[derive(Debug, Clone)]
+pub(super) struct ParsedChecklist {
+    pub checklist: Checklist,
+    /// The delimiter starting the checklist
+    pub start: Range<usize>,
+    /// The delimiter ending the checklist, `None` when it ran to the next checklist or the end
+    pub end: Option<Range<usize>>,
+    /// One span per item, in the order of [`Checklist::walk`]
+    pub spans: Vec<ItemSpan>,
+}
+
+/// Parses every delimited checklist in the Markdown, including those without items
+pub(super) fn parse_markdown(markdown_input: &str) -> Vec<ParsedChecklist> {
+    let mut checklists = Vec::<ParsedChecklist>::new();
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TASKLISTS);
+    let parser = Parser::new_ext(markdown_input, options).into_offset_iter();
+    let mut current: Option<ParsedChecklist> = None;
+    // One entry per open list item along with its slot in `spans`,
+    // `None` when the item is outside of a checklist
+    let mut item_stack: Vec<Option<(ChecklistItem, usize)>> = Vec::new();
+    // Spans of items in the order they start, `None` once an item is dropped
+    let mut spans: Vec<Option<ItemSpan>> = Vec::new();
+    // HTML blocks arrive a line at a time, delimiters can span several lines
+    let mut html_block: Option<(String, Range<usize>)> = None;
+    for (event, range) in parser {
+        match &event {
+            Event::Start(Tag::HtmlBlock) => html_block = Some((String::new(), range)),
+            Event::Html(string) => {
+                if let Some((html, _)) = html_block.as_mut() {
+                    html.push_str(string);
+                }
+            }
+            Event::Start(Tag::Item) => {
+                if current.is_some() {
+                    debug!("[extract_checklist][event:start] ChecklistItem found");
+                    spans.push(Some(ItemSpan {
+                        item: range,
+                        marker: None,
+                    }));
+                    item_stack.push(Some((ChecklistItem::default(), spans.len() - 1)));
+                } else {
+                    item_stack.push(None);
+                }
+            }
+            Event::Text(string) => {
+                if let Some(Some((checklist_item, _))) = item_stack.last_mut() {
+                    debug!("[extract_checklist][event:text] ChecklistItem Found text: {string:?}");
+                    checklist_item.text.push_str(string);
+                }
+            }
+            Event::InlineHtml(html) => {
+                if let Some(Some((checklist_item, _))) = item_stack.last_mut() {
+                    debug!(
+                        "[extract_checklist][event:inlinehtml] ChecklistItem Found HTML: {html:?}"
+                    );
+                    apply_item_attribute(checklist_item, html);
+                }
+            }
+            Event::TaskListMarker(status) => {
+                if let Some(Some((checklist_item, slot))) = item_stack.last_mut() {
+                    debug!(
+                        "[extract_checklist][event:tasklistmarker] ChecklistItem Found TaskListMarker: {status:?}"
+                    );
+                    checklist_item.resolved = *status;
+                    if let Some(span) = &mut spans[*slot] {
+                        span.marker = Some(range);
+                    }
+                }
+            }
+            Event::End(TagEnd::Item) => {
+                if let Some(Some((checklist_item, slot))) = item_stack.pop() {
+                    let added = match current.as_mut() {
+                        Some(parsed) => {
+                            add_item(&mut parsed.checklist.items, &mut item_stack, checklist_item)
+                        }
+                        None => false,
+                    };
+                    if !added {
+                        spans[slot] = None;
+                    }
+                }
+            }
+            Event::End(TagEnd::HtmlBlock) => {
+                let Some((string, range)) = html_block.take() else {
+                    continue;
+                };
+                if !string.contains("checklist") || !string.contains("<!--") {
+                    continue;
+                }
+                // An unnamed delimiter or one repeating the name ends the current checklist,
+                // a differently named one ends it and starts a new checklist
+                let name = parse_checklist_name(&string);
+                match current.take() {
+                    Some(mut parsed)
+                        if name.is_none() || name.as_ref() == Some(&parsed.checklist.name) =>
+                    {
+                        debug!("[extract_checklist][event:html] Found end of checklist");
+                        parsed.end = Some(range);
+                        checklists.push(finish_checklist(parsed, &mut spans));
+                    }
+                    previous => {
+                        if let Some(parsed) = previous {
+                            debug!(
+                                "[extract_checklist][event:html] Found start of checklist {name:?}, ending {:?}",
+                                parsed.checklist.name
+                            );
+                            checklists.push(finish_checklist(parsed, &mut spans));
+                        }
+                        debug!("[extract_checklist][event:html] Found start of checklist");
+                        current = Some(ParsedChecklist {
+                            checklist: Checklist {
+                                mode: extract_checklist_mode(&string),
+                                expiry: extract_checklist_expiry(&string),
+                                key: extract_checklist_key(&string),
+                                name: extract_checklist_name(string),
+                                ..Default::default()
+                            },
+                            start: range,
+                            end: None,
+                            spans: Vec::new(),
+                        });
+                    }
+                }
+            }
+            _ => (),
+        };
+    }
+    if let Some(parsed) = current {
+        checklists.push(finish_checklist(parsed, &mut spans));
+    }
+
+    checklists
+}
+
+/// Moves the spans of the items kept in a checklist into it
+fn finish_checklist(
+    mut parsed: ParsedChecklist,
+    spans: &mut Vec<Option<ItemSpan>>,
+) -> ParsedChecklist {
+    parsed.spans = spans.drain(..).flatten().collect();
+    parsed
+}
+
+/// Attaches a finished item to its parent item, or to the checklist when it is top level
+/// Returns `false` when the item was dropped for having no text
+fn add_item(
+    checklist_items: &mut Vec<ChecklistItem>,
+    item_stack: &mut [Option<(ChecklistItem, usize)>],
+    mut checklist_item: ChecklistItem,
+) -> bool {
+    extract_item_id(&mut checklist_item);
+    // Looked for before the response is split off, the marker can end either side of `::`
+    if checklist_item.text.contains("[OPTIONAL]") {
+        debug!(
+            "[set_optionality] Setting {:?} to optional",
+            checklist_item.text
+        );
+        checklist_item.optional = true;
+    }
+    extract_item_response(&mut checklist_item);
+
+    let siblings = match item_stack.last_mut() {
+        Some(Some((parent, _))) => &mut parent.children,
+        _ => checklist_items,
+    };
+
+    if checklist_item.text.is_empty() {
+        // Items without text can't be ticked, so their children move up a level
+        siblings.append(&mut checklist_item.children);
+        false
+    } else {
+        debug!("[extract_checklist][event:end] Adding ChecklistItem: {checklist_item:?}");
+        siblings.push(checklist_item);
+        true
+    }
+}
+
+/// Moves a trailing `{#id}` out of the item's text into its id
+fn extract_item_id(checklist_item: &mut ChecklistItem) {
+    let text = checklist_item.text.trim_end();
+    if let Some(start) = text.rfind("{#").filter(|_| text.ends_with('}')) {
+        let id = &text[start + 2..text.len() - 1];
+        if !id.is_empty() && !id.contains(char::is_whitespace) {
+            debug!("[extract_item_id] Found id {id:?} for {text:?}");
+            checklist_item.id = Some(id.to_string());
+            checklist_item.text.truncate(start);
+        }
+    }
+    let trimmed_length = checklist_item.text.trim_end().len();
+    checklist_item.text.truncate(trimmed_length);
+}
+
+/// Splits a `challenge :: response` item into its challenge text and response
+fn extract_item_response(checklist_item: &mut ChecklistItem) {
+    let Some((challenge, response)) = checklist_item.text.split_once(" :: ") else {
+        return;
+    };
+    let (challenge, response) = (challenge.trim(), response.trim());
+    if challenge.is_empty() || response.is_empty() {
+        return;
+    }
+    debug!("[extract_item_response] Found response {response:?} for {challenge:?}");
+    checklist_item.response = Some(response.to_string());
+    checklist_item.text = challenge.to_string();
+}
+
+/// Applies an inline `<!-- key: value -->` comment to the item it is written in
+fn apply_item_attribute(checklist_item: &mut ChecklistItem, html: &str) {
+    let Some((key, value)) = html
+        .trim()
+        .strip_prefix("<!--")
+        .and_then(|comment| comment.strip_suffix("-->"))
+        .and_then(|comment| comment.split_once(':'))
+    else {
+        return;
+    };
+    let value = value.trim();
+    match key.trim() {
+        "id" if !value.is_empty() => checklist_item.id = Some(value.to_string()),
+        "run" if !value.is_empty() => checklist_item.run = Some(value.to_string()),
+        "expires" => match parse_duration(value) {
+            Ok(expires) => checklist_item.expiry.expires = Some(expires),
+            Err(error) => warn!("[apply_item_attribute] {error}, ignoring it"),
+        },
+        "reset" => match value.parse() {
+            Ok(reset) => checklist_item.expiry.reset = Some(reset),
+            Err(error) => warn!("[apply_item_attribute] {error}, ignoring it"),
+        },
+        key => debug!("[apply_item_attribute] Ignoring attribute {key:?}"),
     }
 }
 
 pub(super) fn extract_checklist_name(input_string: String) -> String {
-    debug!("[extract_checklist_name] Extracting name from : {input_string:?}");
-    let mut name = String::from("");
-    // Remove HTML comment brackets
-    let input_string = input_string.replace(&['<', '!', '-', '>'][..], "");
+    parse_checklist_name(&input_string).unwrap_or_else(|| String::from("checklist"))
+}
+
+fn extract_checklist_mode(input_string: &str) -> ChecklistMode {
+    parse_checklist_mode(input_string)
+        .unwrap_or_else(|error| {
+            warn!("[extract_checklist_mode] {error}, using free");
+            None
+        })
+        .unwrap_or_default()
+}
+
+/// Returns the mode given by a delimiter, `None` when it gives none
+pub(super) fn parse_checklist_mode(
+    input_string: &str,
+) -> std::result::Result<Option<ChecklistMode>, String> {
+    let Some(delimiter) = parse_delimiter(input_string) else {
+        return Ok(None);
+    };
+    match delimiter.get("mode") {
+        Some(mode) => match mode.as_str() {
+            Some(mode) => mode.parse().map(Some),
+            None => Err(format!("checklist mode {mode} is not a string")),
+        },
+        None => Ok(None),
+    }
+}
+
+fn extract_checklist_expiry(input_string: &str) -> Expiry {
+    parse_checklist_expiry(input_string).unwrap_or_else(|error| {
+        warn!("[extract_checklist_expiry] {error}, resolutions won't expire");
+        Expiry::default()
+    })
+}
+
+/// Returns the `expires` and `reset` rules given by a delimiter
+pub(super) fn parse_checklist_expiry(input_string: &str) -> std::result::Result<Expiry, String> {
+    let Some(delimiter) = parse_delimiter(input_string) else {
+        return Ok(Expiry::default());
+    };
+    let string_value = |key: &str| match delimiter.get(key) {
+        Some(value) => value
+            .as_str()
+            .map(Some)
+            .ok_or_else(|| format!("checklist {key} {value} is not a string")),
+        None => Ok(None),
+    };
+    Ok(Expiry {
+        expires: string_value("expires")?.map(parse_duration).transpose()?,
+        reset: string_value("reset")?.map(str::parse).transpose()?,
+    })
+}
+
+fn extract_checklist_key(input_string: &str) -> Option<String> {
+    parse_checklist_key(input_string).unwrap_or_else(|error| {
+        warn!("[extract_checklist_key] {error}, saving by path and name");
+        None
+    })
+}
+
+/// Returns the save key given by a delimiter, `None` when it gives none
+pub(super) fn parse_checklist_key(
+    input_string: &str,
+) -> std::result::Result<Option<String>, String> {
+    let Some(delimiter) = parse_delimiter(input_string) else {
+        return Ok(None);
+    };
+    match delimiter.get("key") {
+        Some(key) => match key.as_str() {
+            Some(key) if !key.is_empty() => Ok(Some(key.to_string())),
+            _ => Err(format!("checklist key {key} is not a non-empty string")),
+        },
+        None => Ok(None),
+    }
+}
+
+/// Parses the TOML inside a delimiter comment
+fn parse_delimiter(input_string: &str) -> Option<Table> {
     let input_string = input_string.trim();
-    let result = input_string.parse::<Table>();
-    match result {
-        Ok(value) => match value.get("checklist") {
-            Some(val) => {
+    let content = input_string
+        .strip_prefix("<!--")
+        .and_then(|content| content.strip_suffix("-->"))
+        .unwrap_or(input_string);
+    content.trim().parse::<Table>().ok()
+}
+
+/// Returns the name given by a delimiter, or `None` when it has no valid name
+pub(super) fn parse_checklist_name(input_string: &str) -> Option<String> {
+    debug!("[extract_checklist_name] Extracting name from : {input_string:?}");
+    match parse_delimiter(input_string) {
+        Some(value) => match value.get("checklist").and_then(|val| val.as_str()) {
+            Some(val) if !val.is_empty() => {
                 debug!("[extract_checklist_name] Found: {val:?}");
-                name = val.as_str().unwrap().to_string();
+                Some(val.to_string())
             }
-            None => {
+            _ => {
                 debug!("[extract_checklist_name] Found some TOML but name wasn't following spec");
+                None
             }
         },
-        Err(_err) => {
+        None => {
             debug!("[extract_checklist_name] No Name found");
+            None
         }
     }
-
-    if name.is_empty() {
-        name = String::from("checklist");
-    }
-
-    name
 }
@@ -0,0 +1,179 @@
+use super::model::Checklist;
+use crate::error::{Format, KneeboardError, Result};
+use log::debug;
+use serde::Serialize;
+use std::{fmt, path::PathBuf, str::FromStr};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReportFormat {
+    Json,
+    Junit,
+    Tap,
+}
+
+/// Where and how to write a report, parsed from `<format>[=<path>]`
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReportTarget {
+    pub format: ReportFormat,
+    pub path: Option<PathBuf>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Totals {
+    pub total: usize,
+    pub resolved: usize,
+    pub unresolved: usize,
+    pub optional: usize,
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    #[serde(flatten)]
+    checklist: &'a Checklist,
+    totals: Totals,
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
+        match input {
+            "json" => Ok(ReportFormat::Json),
+            "junit" => Ok(ReportFormat::Junit),
+            "tap" => Ok(ReportFormat::Tap),
+            _ => Err(format!(
+                "unknown report format {input:?}, expected json, junit or tap"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ReportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReportFormat::Json => write!(f, "json"),
+            ReportFormat::Junit => write!(f, "junit"),
+            ReportFormat::Tap => write!(f, "tap"),
+        }
+    }
+}
+
+impl FromStr for ReportTarget {
+    type Err = String;
+
+    fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
+        let (format, path) = match input.split_once('=') {
+            Some((format, path)) if !path.is_empty() => (format, Some(PathBuf::from(path))),
+            Some((format, _)) => (format, None),
+            None => (input, None),
+        };
+        Ok(ReportTarget {
+            format: format.parse()?,
+            path,
+        })
+    }
+}
+
+impl Checklist {
+    pub fn get_totals(&self) -> Totals {
+        let items = self.walk();
+        Totals {
+            total: items.len(),
+            resolved: items.iter().filter(|(_, i)| i.is_resolved()).count(),
+            unresolved: self.get_count_unresolved(),
+            optional: items.iter().filter(|(_, i)| i.optional).count(),
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        let report = Report {
+            checklist: self,
+            totals: self.get_totals(),
+        };
+        serde_json::to_string_pretty(&report).map_err(|error| KneeboardError::Serialize {
+            format: Format::Json,
+            source: Box::new(error),
+        })
+    }
+
+    /// JUnit XML with a test case per item, unresolved required items fail
+    /// and unresolved optional items are skipped
+    pub fn to_junit(&self) -> String {
+        let items = self.walk();
+        let failures = items
+            .iter()
+            .filter(|(_, i)| !i.optional && !i.is_resolved())
+            .count();
+        let skipped = items
+            .iter()
+            .filter(|(_, i)| i.optional && !i.is_resolved())
+            .count();
+        let name = escape_xml(&self.name);
+        let counts = format!(
+            "tests=\"{}\" failures=\"{failures}\" skipped=\"{skipped}\"",
+            items.len()
+        );
+
+        let mut junit = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        junit.push_str(&format!("<testsuites name=\"kneeboard\" {counts}>\n"));
+        junit.push_str(&format!("  <testsuite name=\"{name}\" {counts}>\n"));
+        for (_, item) in items {
+            let text = escape_xml(&item.text);
+            if item.is_resolved() {
+                junit.push_str(&format!(
+                    "    <testcase classname=\"{name}\" name=\"{text}\"/>\n"
+                ));
+                continue;
+            }
+            let outcome = if item.optional {
+                "<skipped message=\"optional item not resolved\"/>"
+            } else {
+                "<failure message=\"item not resolved\"/>"
+            };
+            junit.push_str(&format!(
+                "    <testcase classname=\"{name}\" name=\"{text}\">\n      {outcome}\n    </testcase>\n"
+            ));
+        }
+        junit.push_str("  </testsuite>\n</testsuites>\n");
+        junit
+    }
+
+    /// TAP version 13 with a test point per item, unresolved optional items are skipped
+    pub fn to_tap(&self) -> String {
+        let items = self.walk();
+        let mut tap = format!("TAP version 13\n1..{}\n", items.len());
+        for (number, (depth, item)) in items.into_iter().enumerate() {
+            let status = if item.is_resolved() || item.optional {
+                "ok"
+            } else {
+                "not ok"
+            };
+            // Nested items are prefixed so the grouping stays visible
+            let description = format!("{}{}", "> ".repeat(depth), item.text).replace('#', "\\#");
+            tap.push_str(&format!("{status} {} - {description}", number + 1));
+            if item.optional && !item.is_resolved() {
+                tap.push_str(" # SKIP optional item not resolved");
+            }
+            tap.push('\n');
+        }
+        tap
+    }
+
+    pub fn to_report(&self, format: ReportFormat) -> Result<String> {
+        debug!("[to_report] Generating {format} report for {:?}", self.name);
+        match format {
+            ReportFormat::Json => self.to_json(),
+            ReportFormat::Junit => Ok(self.to_junit()),
+            ReportFormat::Tap => Ok(self.to_tap()),
+        }
+    }
+}
+
+fn escape_xml(input: &str) -> String {
+    input
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
@@ -0,0 +1,94 @@
+use super::model::Checklist;
+use crate::error::{KneeboardError, Result};
+use log::debug;
+
+impl Checklist {
+    /// Finds the items picked by `selector`, as indexes into [`Checklist::walk`].
+    ///
+    /// A selector is tried as an exact text or id, then as a 1-based item number,
+    /// then as a glob using `*` and `?` when it has either, and last as a prefix of a
+    /// single item's text
+    pub fn select_items(&self, selector: &str) -> Result<Vec<usize>> {
+        let items = self.walk();
+        let find = |matches: &dyn Fn(&str) -> bool| -> Vec<usize> {
+            items
+                .iter()
+                .enumerate()
+                .filter(|(_, (_, item))| matches(&item.text))
+                .map(|(index, _)| index)
+                .collect()
+        };
+
+        let exact: Vec<usize> = items
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, item))| item.matches(selector))
+            .map(|(index, _)| index)
+            .collect();
+        if !exact.is_empty() {
+            return Ok(exact);
+        }
+
+        if let Ok(number) = selector.parse::<usize>() {
+            return match number.checked_sub(1).filter(|index| *index < items.len()) {
+                Some(index) => Ok(vec![index]),
+                None => Err(KneeboardError::validation(format!(
+                    "no item number {number} in {:?}, it has {} items",
+                    self.name,
+                    items.len()
+                ))),
+            };
+        }
+
+        let selected = if selector.contains(['*', '?']) {
+            find(&|text| glob_match(selector, text))
+        } else {
+            let selected = find(&|text| text.starts_with(selector));
+            if selected.len() > 1 {
+                let texts: Vec<&str> = selected.iter().map(|i| items[*i].1.text.as_str()).collect();
+                return Err(KneeboardError::validation(format!(
+                    "{selector:?} is the start of several items {texts:?}"
+                )));
+            }
+            selected
+        };
+        debug!("[select_items] {selector:?} selected {selected:?}");
+        if selected.is_empty() {
+            return Err(KneeboardError::validation(format!(
+                "no item matching {selector:?} in {:?}",
+                self.name
+            )));
+        }
+        Ok(selected)
+    }
+}
+
+/// Matches `text` against a glob where `*` is any run of characters and `?` any one character
+pub(super) fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Where to resume from after the last `*` when the text stops matching
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, star_t)) => {
+                    p = star + 1;
+                    t = star_t + 1;
+                    backtrack = Some((star, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
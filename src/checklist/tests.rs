@@ -1,4 +1,10 @@
+use crate::checklist::expiry::{Expiry, Reset, parse_duration};
+use crate::checklist::lint::Diagnostic;
 use crate::checklist::model::extract_checklist_name;
+use crate::checklist::report::{ReportFormat, Totals};
+use crate::checklist::select::glob_match;
+use crate::error::{Format, KneeboardError, Location};
+use std::time::Duration;
 
 use super::*;
 
@@ -6,6 +12,7 @@ fn generate_test_checklist(count: u128, name: String, optional: Option<bool>) ->
     let mut test_checklist = Checklist {
         items: Vec::<ChecklistItem>::new(),
         name: name.clone(),
+        ..Default::default()
     };
 
     for i in 0..count {
@@ -13,6 +20,7 @@ fn generate_test_checklist(count: u128, name: String, optional: Option<bool>) ->
             text: format!("{} item {:}", &name, i),
             optional: optional.unwrap_or(false),
             resolved: false,
+            ..Default::default()
         })
     }
 
@@ -32,11 +40,13 @@ Example paragraph with **lorem** _ipsum_ text.
     let mut test_checklist = Checklist {
         name: "checklist".to_string(),
         items: Vec::new(),
+        ..Default::default()
     };
     test_checklist.items.push(ChecklistItem {
         text: "test checklist item".to_string(),
         optional: false,
         resolved: true,
+        ..Default::default()
     });
     assert_eq!(test_checklist.name, checklist.name);
     assert_eq!(test_checklist.items, checklist.items)
@@ -52,11 +62,13 @@ fn from_markdown_single_item_marked_as_resolved() {
     let mut test_checklist = Checklist {
         name: "checklist".to_string(),
         items: Vec::new(),
+        ..Default::default()
     };
     test_checklist.items.push(ChecklistItem {
         text: "test checklist item".to_string(),
         optional: false,
         resolved: true,
+        ..Default::default()
     });
     assert_eq!(test_checklist.name, checklist.name);
     assert_eq!(test_checklist.items, checklist.items)
@@ -72,11 +84,13 @@ fn from_markdown_single_item_marked_as_unresolved() {
     let mut test_checklist = Checklist {
         name: "checklist".to_string(),
         items: Vec::new(),
+        ..Default::default()
     };
     test_checklist.items.push(ChecklistItem {
         text: "test checklist item".to_string(),
         optional: false,
         resolved: false,
+        ..Default::default()
     });
     assert_eq!(test_checklist.name, checklist.name);
     assert_eq!(test_checklist.items, checklist.items)
@@ -92,11 +106,13 @@ fn from_markdown_single_item_invalid_checkbox() {
     let mut test_checklist = Checklist {
         name: "checklist".to_string(),
         items: Vec::new(),
+        ..Default::default()
     };
     test_checklist.items.push(ChecklistItem {
         text: "[] test checklist item".to_string(),
         optional: false,
         resolved: false,
+        ..Default::default()
     });
     assert_eq!(test_checklist.name, checklist.name);
     assert_eq!(test_checklist.items, checklist.items)
@@ -112,11 +128,13 @@ fn from_markdown_single_item_no_checkbox() {
     let mut test_checklist = Checklist {
         name: "checklist".to_string(),
         items: Vec::new(),
+        ..Default::default()
     };
     test_checklist.items.push(ChecklistItem {
         text: "test checklist item".to_string(),
         optional: false,
         resolved: false,
+        ..Default::default()
     });
     assert_eq!(test_checklist.name, checklist.name);
     assert_eq!(test_checklist.items, checklist.items)
@@ -134,11 +152,13 @@ Example paragraph with **lorem** _ipsum_ text.
     let mut test_checklist = Checklist {
         name: "checklist".to_string(),
         items: Vec::new(),
+        ..Default::default()
     };
     test_checklist.items.push(ChecklistItem {
         text: "test checklist item [OPTIONAL]".to_string(),
         optional: true,
         resolved: true,
+        ..Default::default()
     });
     assert_eq!(test_checklist.name, checklist.name);
     assert_eq!(test_checklist.items, checklist.items);
@@ -158,16 +178,19 @@ Example paragraph with **lorem** _ipsum_ text.
     let mut test_checklist = Checklist {
         name: "checklist".to_string(),
         items: Vec::new(),
+        ..Default::default()
     };
     test_checklist.items.push(ChecklistItem {
         text: "test checklist item 1".to_string(),
         optional: false,
         resolved: true,
+        ..Default::default()
     });
     test_checklist.items.push(ChecklistItem {
         text: "test checklist item 2".to_string(),
         optional: false,
         resolved: false,
+        ..Default::default()
     });
 
     // A task marker must have a space in between the brackets
@@ -175,6 +198,7 @@ Example paragraph with **lorem** _ipsum_ text.
         text: "[] test checklist item 3".to_string(),
         optional: false,
         resolved: false,
+        ..Default::default()
     });
     assert_eq!(test_checklist.name, checklist.name);
     assert_eq!(test_checklist.items, checklist.items)
@@ -196,16 +220,19 @@ Example paragraph with **lorem** _ipsum_ text.
     let mut test_checklist = Checklist {
         name: "".to_string(),
         items: Vec::new(),
+        ..Default::default()
     };
     test_checklist.items.push(ChecklistItem {
         text: "test checklist item 1".to_string(),
         optional: false,
         resolved: true,
+        ..Default::default()
     });
     test_checklist.items.push(ChecklistItem {
         text: "test checklist item 2".to_string(),
         optional: false,
         resolved: true,
+        ..Default::default()
     });
     assert_eq!(test_checklist.items, checklist.items)
 }
@@ -223,21 +250,25 @@ fn create_new_checklist_from_markdown_with_nested_items() {
     let mut test_checklist = Checklist {
         name: "".to_string(),
         items: Vec::new(),
+        ..Default::default()
     };
     test_checklist.items.push(ChecklistItem {
         text: "test checklist item".to_string(),
         optional: false,
         resolved: false,
-    });
-    test_checklist.items.push(ChecklistItem {
-        text: "test checklist nested item 1".to_string(),
-        optional: false,
-        resolved: false,
-    });
-    test_checklist.items.push(ChecklistItem {
-        text: "test checklist nested item 2".to_string(),
-        optional: false,
-        resolved: false,
+        children: vec![ChecklistItem {
+            text: "test checklist nested item 1".to_string(),
+            optional: false,
+            resolved: false,
+            children: vec![ChecklistItem {
+                text: "test checklist nested item 2".to_string(),
+                optional: false,
+                resolved: false,
+                ..Default::default()
+            }],
+            ..Default::default()
+        }],
+        ..Default::default()
     });
 
     assert_eq!(test_checklist.items, checklist.items)
@@ -263,6 +294,125 @@ fn create_new_checklist_from_markdown_with_multi_nested_items() {
     assert_eq!(checklist.get_count_unresolved(), 9)
 }
 
+#[test_log::test]
+fn create_new_checklist_from_markdown_with_nested_items_keeps_grouping() {
+    let markdown_input = r#"
+<!-- checklist -->
+- [ ] phase 1
+    - [x] phase 1 step 1
+    - [ ] phase 1 step 2
+- [ ] phase 2
+    - [ ] phase 2 step 1
+<!-- checklist -->
+        "#;
+    let checklist = Checklist::from_markdown(String::from(markdown_input)).unwrap();
+    assert_eq!(checklist.items.len(), 2);
+    assert_eq!(checklist.items[0].children.len(), 2);
+    assert_eq!(checklist.items[1].children.len(), 1);
+    assert_eq!(checklist.walk().len(), 5);
+    assert_eq!(checklist.walk()[2].0, 1);
+    assert_eq!(checklist.walk()[2].1.text, "phase 1 step 2");
+}
+
+#[test_log::test]
+fn parent_item_resolved_only_when_required_children_resolved() {
+    let mut parent = ChecklistItem {
+        text: "parent".to_string(),
+        resolved: true,
+        children: vec![
+            ChecklistItem {
+                text: "child".to_string(),
+                ..Default::default()
+            },
+            ChecklistItem {
+                text: "optional child [OPTIONAL]".to_string(),
+                optional: true,
+                ..Default::default()
+            },
+        ],
+        ..Default::default()
+    };
+    assert!(!parent.is_resolved());
+
+    parent.children[0].resolved = true;
+    assert!(parent.is_resolved());
+
+    let checklist = Checklist {
+        name: "checklist".to_string(),
+        items: vec![parent],
+        ..Default::default()
+    };
+    assert_eq!(checklist.get_count_unresolved(), 0);
+}
+
+#[test_log::test]
+fn count_unresolved_parent_with_unresolved_child() {
+    let markdown_input = r#"
+<!-- checklist -->
+- [x] parent
+    - [ ] child
+        "#;
+    let checklist = Checklist::from_markdown(String::from(markdown_input)).unwrap();
+    assert_eq!(checklist.get_count_unresolved(), 2);
+}
+
+#[test_log::test]
+fn from_markdown_all_multiple_named_checklists() {
+    let markdown_input = r#"
+<!-- checklist = 'pre-commit' -->
+- [ ] pre-commit item
+<!-- checklist = 'pre-commit' -->
+Some text in between
+<!-- checklist = 'release' -->
+- [ ] release item 1
+- [x] release item 2
+<!-- checklist = 'release' -->
+        "#;
+    let checklists = Checklist::from_markdown_all(String::from(markdown_input)).unwrap();
+    assert_eq!(checklists.len(), 2);
+    assert_eq!(checklists[0].name, "pre-commit");
+    assert_eq!(checklists[0].items.len(), 1);
+    assert_eq!(checklists[1].name, "release");
+    assert_eq!(checklists[1].items.len(), 2);
+}
+
+#[test_log::test]
+fn from_markdown_all_differently_named_delimiter_starts_new_checklist() {
+    let markdown_input = r#"
+<!-- checklist = 'first' -->
+- [ ] first item
+<!-- checklist = 'second' -->
+- [ ] second item
+<!-- checklist -->
+- [ ] not a checklist item
+        "#;
+    let checklists = Checklist::from_markdown_all(String::from(markdown_input)).unwrap();
+    assert_eq!(checklists.len(), 2);
+    assert_eq!(checklists[0].items[0].text, "first item");
+    assert_eq!(checklists[1].name, "second");
+    assert_eq!(checklists[1].items.len(), 1);
+}
+
+#[test_log::test]
+fn from_markdown_named_selects_checklist() {
+    let markdown_input = r#"
+<!-- checklist = 'first' -->
+- [ ] first item
+<!-- checklist = 'first' -->
+<!-- checklist = 'second' -->
+- [ ] second item
+<!-- checklist = 'second' -->
+        "#;
+    let checklist = Checklist::from_markdown_named(String::from(markdown_input), "second").unwrap();
+    assert_eq!(checklist.name, "second");
+    assert_eq!(checklist.items[0].text, "second item");
+
+    let checklist = Checklist::from_markdown(String::from(markdown_input)).unwrap();
+    assert_eq!(checklist.name, "first");
+
+    assert!(Checklist::from_markdown_named(String::from(markdown_input), "third").is_err());
+}
+
 #[test_log::test]
 fn create_new_checklist_from_markdown_single_item_containing_markdown_formating() {
     let markdown_input = r#"
@@ -275,11 +425,13 @@ Example paragraph with **lorem** _ipsum_ text.
     let mut test_checklist = Checklist {
         name: "checklist".to_string(),
         items: Vec::new(),
+        ..Default::default()
     };
     test_checklist.items.push(ChecklistItem {
         text: "Example paragraph with lorem ipsum text.".to_string(),
         optional: false,
         resolved: false,
+        ..Default::default()
     });
     assert_eq!(test_checklist.items, checklist.items)
 }
@@ -303,16 +455,19 @@ fn save_and_load_checklist() {
     let mut test_checklist = Checklist {
         name: "test_checklist".to_string(),
         items: Vec::new(),
+        ..Default::default()
     };
     test_checklist.items.push(ChecklistItem {
         text: "test checklist item 1".to_string(),
         optional: false,
         resolved: false,
+        ..Default::default()
     });
     test_checklist.items.push(ChecklistItem {
         text: "test checklist item 2".to_string(),
         optional: false,
         resolved: false,
+        ..Default::default()
     });
     let toml_string = test_checklist.to_toml().unwrap();
     assert_eq!(toml_string,"name = \"test_checklist\"\n\n[[items]]\ntext = \"test checklist item 1\"\noptional = false\nresolved = false\n\n[[items]]\ntext = \"test checklist item 2\"\noptional = false\nresolved = false\n".to_string());
@@ -321,6 +476,204 @@ fn save_and_load_checklist() {
     assert_eq!(reconstructed_checklist.items, test_checklist.items);
 }
 
+#[test_log::test]
+fn save_and_load_nested_checklist() {
+    let markdown_input = r#"
+<!-- checklist -->
+- [ ] parent
+    - [x] child
+        "#;
+    let checklist = Checklist::from_markdown(String::from(markdown_input)).unwrap();
+    let toml_string = checklist.to_toml().unwrap();
+    assert!(toml_string.contains("[[items.children]]"));
+
+    let reconstructed_checklist = Checklist::from_toml(toml_string).unwrap();
+    assert_eq!(reconstructed_checklist.items, checklist.items);
+}
+
+#[test_log::test]
+fn merge_checklist_nested_items() {
+    let markdown_input = r#"
+<!-- checklist -->
+- [ ] parent
+    - [ ] child
+        "#;
+    let mut checklist = Checklist::from_markdown(String::from(markdown_input)).unwrap();
+    let mut saved_checklist = checklist.clone();
+    saved_checklist.items[0].children[0].resolved = true;
+
+    checklist.merge_checklist(&saved_checklist);
+    assert!(checklist.items[0].children[0].resolved);
+    assert!(!checklist.items[0].resolved);
+}
+
+#[test_log::test]
+fn write_back_only_touches_task_list_markers() {
+    let markdown_input = r#"# Release
+Intro with - [ ] a list outside the checklist
+<!-- checklist = 'release' -->
+- [ ] step 1
+    - [X] step 1.1
+- step without checkbox
+* [x] step 2
+<!-- checklist = 'release' -->
+- [ ] not a checklist item
+"#;
+    let mut checklist = Checklist::from_markdown(String::from(markdown_input)).unwrap();
+    checklist.walk_mut(|_, item| item.resolved = !item.resolved);
+
+    let output = checklist.write_back(markdown_input).unwrap();
+    assert_eq!(
+        output,
+        r#"# Release
+Intro with - [ ] a list outside the checklist
+<!-- checklist = 'release' -->
+- [x] step 1
+    - [ ] step 1.1
+- step without checkbox
+* [ ] step 2
+<!-- checklist = 'release' -->
+- [ ] not a checklist item
+"#
+    );
+}
+
+#[test_log::test]
+fn write_back_unchanged_checklist_keeps_document() {
+    let markdown_input = "<!-- checklist -->\n- [X] step 1\n- [ ] step 2\n";
+    let checklist = Checklist::from_markdown(String::from(markdown_input)).unwrap();
+    assert_eq!(
+        checklist.write_back(markdown_input).unwrap(),
+        markdown_input
+    );
+}
+
+#[test_log::test]
+fn write_back_mismatched_checklist() {
+    let markdown_input = "<!-- checklist -->\n- [ ] step 1\n";
+    let mut checklist = Checklist::from_markdown(String::from(markdown_input)).unwrap();
+    checklist.items[0].text = "step 2".to_string();
+    assert_eq!(
+        checklist
+            .write_back(markdown_input)
+            .unwrap_err()
+            .to_string(),
+        "invalid checklist at line 2, column 1: checklist \"checklist\" no longer matches the Markdown"
+    );
+
+    // Nested items are pointed at past their indentation, items missing from the Markdown at
+    // the end of the checklist
+    let markdown_input = "<!-- checklist -->\n- [ ] step 1\n  - [ ] step 1a\n<!-- checklist -->\n";
+    let checklist = Checklist::from_markdown(String::from(markdown_input)).unwrap();
+    let mut renamed = checklist.clone();
+    renamed.items[0].children[0].text = "step 1b".to_string();
+    let error = renamed.write_back(markdown_input).unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .starts_with("invalid checklist at line 3, column 3:")
+    );
+    let mut added = checklist.clone();
+    added.items.push(ChecklistItem {
+        text: "step 2".to_string(),
+        ..Default::default()
+    });
+    let error = added.write_back(markdown_input).unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .starts_with("invalid checklist at line 4, column 1:")
+    );
+}
+
+#[test_log::test]
+fn from_markdown_item_ids() {
+    let markdown_input = r#"
+<!-- checklist -->
+- [ ] run the tests {#tests}
+- [ ] update the changelog <!-- id: changelog -->
+- [ ] no id {# not an id}
+        "#;
+    let checklist = Checklist::from_markdown(String::from(markdown_input)).unwrap();
+    assert_eq!(checklist.items[0].text, "run the tests");
+    assert_eq!(checklist.items[0].id, Some("tests".to_string()));
+    assert_eq!(checklist.items[1].text, "update the changelog");
+    assert_eq!(checklist.items[1].id, Some("changelog".to_string()));
+    assert_eq!(checklist.items[2].text, "no id {# not an id}");
+    assert_eq!(checklist.items[2].id, None);
+}
+
+#[test_log::test]
+fn merge_checklist_matches_by_id_when_text_changes() {
+    let saved_markdown = "<!-- checklist -->\n- [x] run teh tests {#tests}\n";
+    let saved_checklist = Checklist::from_markdown(String::from(saved_markdown)).unwrap();
+    let markdown_input = "<!-- checklist -->\n- [ ] run all of the tests please {#tests}\n";
+    let mut checklist = Checklist::from_markdown(String::from(markdown_input)).unwrap();
+
+    let report = checklist.merge_checklist(&saved_checklist);
+    assert!(checklist.items[0].resolved);
+    assert!(report.unmatched.is_empty());
+}
+
+#[test_log::test]
+fn merge_checklist_matches_similar_text() {
+    let saved_markdown = r#"
+<!-- checklist -->
+- [x] ensure teh commit title is semantic
+- [x] something that was removed
+- [ ] commit is passing tests
+        "#;
+    let saved_checklist = Checklist::from_markdown(String::from(saved_markdown)).unwrap();
+    let markdown_input = r#"
+<!-- checklist -->
+- [ ] ensure the commit title is semantic
+- [ ] commit is passing tests
+- [ ] a brand new item
+        "#;
+    let mut checklist = Checklist::from_markdown(String::from(markdown_input)).unwrap();
+
+    let report = checklist.merge_checklist(&saved_checklist);
+    assert!(checklist.items[0].resolved);
+    assert!(!checklist.items[1].resolved);
+    assert!(!checklist.items[2].resolved);
+    assert_eq!(
+        report.similar,
+        vec![(
+            "ensure teh commit title is semantic".to_string(),
+            "ensure the commit title is semantic".to_string()
+        )]
+    );
+    assert_eq!(
+        report.unmatched,
+        vec!["something that was removed".to_string()]
+    );
+}
+
+#[test_log::test]
+fn merge_checklist_doesnt_match_different_numbers() {
+    let saved_markdown = "<!-- checklist -->\n- [x] Run migration 2\n";
+    let saved_checklist = Checklist::from_markdown(String::from(saved_markdown)).unwrap();
+    let markdown_input = "<!-- checklist -->\n- [ ] Run migration 3\n";
+    let mut checklist = Checklist::from_markdown(String::from(markdown_input)).unwrap();
+
+    let report = checklist.merge_checklist(&saved_checklist);
+    assert!(!checklist.items[0].resolved);
+    assert!(report.similar.is_empty());
+    assert_eq!(report.unmatched, vec!["Run migration 2".to_string()]);
+}
+
+#[test_log::test]
+fn merge_checklist_doesnt_match_ambiguous_text() {
+    let saved_markdown = "<!-- checklist -->\n- [x] update docs A\n- [x] update docs B\n";
+    let saved_checklist = Checklist::from_markdown(String::from(saved_markdown)).unwrap();
+    let markdown_input = "<!-- checklist -->\n- [ ] update docs C\n";
+    let mut checklist = Checklist::from_markdown(String::from(markdown_input)).unwrap();
+
+    let report = checklist.merge_checklist(&saved_checklist);
+    assert!(!checklist.items[0].resolved);
+    assert_eq!(report.unmatched.len(), 2);
+}
+
 #[test_log::test]
 fn generate_test_checklist_test() {
     let test_checklist = generate_test_checklist(300, "test checklist".to_string(), None);
@@ -347,6 +700,73 @@ fn load_non_checklist() {
     let _checklist = Checklist::from_toml("nottoml".to_string()).unwrap();
 }
 
+#[test_log::test]
+fn load_invalid_toml_keeps_location() {
+    let toml_string = "name = \"test_checklist\"\n\n[[items]]\ntext = \"item\"\noptional = nope\n";
+    let error = Checklist::from_toml(toml_string.to_string()).unwrap_err();
+    match &error {
+        KneeboardError::Parse {
+            format: Format::Toml,
+            location: Some(location),
+            source: Some(_),
+            ..
+        } => assert_eq!(
+            *location,
+            Location {
+                line: 5,
+                column: 12
+            }
+        ),
+        _ => panic!("unexpected error {error:?}"),
+    }
+    assert!(
+        error
+            .to_string()
+            .starts_with("failed to parse TOML at line 5, column 12:")
+    );
+}
+
+#[test_log::test]
+fn from_markdown_errors_match_variant() {
+    let error = Checklist::from_markdown(String::from("- [ ] not a checklist")).unwrap_err();
+    assert!(matches!(
+        error,
+        KneeboardError::Parse {
+            format: Format::Markdown,
+            location: None,
+            ..
+        }
+    ));
+    let error =
+        Checklist::from_markdown(String::from("# Empty\n\n<!-- checklist -->\n")).unwrap_err();
+    assert!(matches!(
+        error,
+        KneeboardError::Parse {
+            format: Format::Markdown,
+            location: Some(Location { line: 3, column: 1 }),
+            ..
+        }
+    ));
+
+    let markdown_input = "<!-- checklist -->\n- [ ] item\n";
+    let error =
+        Checklist::from_markdown_named(String::from(markdown_input), "missing").unwrap_err();
+    assert!(matches!(error, KneeboardError::Validation { .. }));
+}
+
+#[test_log::test]
+fn location_from_offset() {
+    let input = "first line\nsecond line\n";
+    assert_eq!(
+        Location::from_offset(input, 0),
+        Location { line: 1, column: 1 }
+    );
+    assert_eq!(
+        Location::from_offset(input, 18),
+        Location { line: 2, column: 8 }
+    );
+}
+
 // extract_checklist_name Tests
 #[test_log::test]
 fn extract_checklist_name_no_name() {
@@ -362,9 +782,715 @@ fn extract_checklist_name_name_following_spec() {
     assert_eq!("test_name", checklist_name)
 }
 
+#[test_log::test]
+fn extract_checklist_name_keeps_hyphens() {
+    let markdown_input = "<!-- checklist = 'pre-commit' -->";
+    let checklist_name = extract_checklist_name(String::from(markdown_input));
+    assert_eq!("pre-commit", checklist_name)
+}
+
+#[test_log::test]
+fn from_markdown_named_finds_legacy_names() {
+    let markdown_input = "<!-- checklist = 'pre-commit' -->\n- [ ] run the tests\n";
+    let checklist =
+        Checklist::from_markdown_named(String::from(markdown_input), "precommit").unwrap();
+    assert_eq!(checklist.name, "pre-commit");
+    assert_eq!(checklist.legacy_name(), "precommit");
+}
+
 #[test_log::test]
 fn extract_checklist_name_name_not_following_spec() {
     let markdown_input = "<!-- blah = 'test_name' -->";
     let checklist_name = extract_checklist_name(String::from(markdown_input));
     assert_eq!("checklist", checklist_name)
 }
+
+// Report Tests
+#[test_log::test]
+fn totals_count_nested_and_optional_items() {
+    let markdown_input = r#"
+<!-- checklist -->
+- [x] item 1
+    - [ ] nested item
+- [x] item 2 [OPTIONAL]
+- [x] item 3
+        "#;
+    let checklist = Checklist::from_markdown(String::from(markdown_input)).unwrap();
+    assert_eq!(
+        checklist.get_totals(),
+        Totals {
+            total: 4,
+            resolved: 2,
+            unresolved: 2,
+            optional: 1,
+        }
+    );
+}
+
+#[test_log::test]
+fn json_report_contains_items_and_totals() {
+    let markdown_input = r#"
+<!-- checklist = 'release' -->
+- [x] item 1
+- [ ] item 2 [OPTIONAL]
+        "#;
+    let checklist = Checklist::from_markdown(String::from(markdown_input)).unwrap();
+    let json_string = checklist.to_report(ReportFormat::Json).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json_string).unwrap();
+    assert_eq!(value["name"], "release");
+    assert_eq!(value["items"][0]["text"], "item 1");
+    assert_eq!(value["items"][0]["resolved"], true);
+    assert_eq!(value["items"][1]["optional"], true);
+    assert_eq!(value["totals"]["total"], 2);
+    assert_eq!(value["totals"]["unresolved"], 0);
+}
+
+#[test_log::test]
+fn junit_report_marks_failures_and_skips() {
+    let markdown_input = r#"
+<!-- checklist = 'release' -->
+- [x] item 1
+- [ ] item <2>
+- [ ] item 3 [OPTIONAL]
+        "#;
+    let checklist = Checklist::from_markdown(String::from(markdown_input)).unwrap();
+    let junit = checklist.to_report(ReportFormat::Junit).unwrap();
+    assert_eq!(
+        junit,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="kneeboard" tests="3" failures="1" skipped="1">
+  <testsuite name="release" tests="3" failures="1" skipped="1">
+    <testcase classname="release" name="item 1"/>
+    <testcase classname="release" name="item &lt;2&gt;">
+      <failure message="item not resolved"/>
+    </testcase>
+    <testcase classname="release" name="item 3 [OPTIONAL]">
+      <skipped message="optional item not resolved"/>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#
+    );
+}
+
+#[test_log::test]
+fn tap_report_marks_failures_and_skips() {
+    let markdown_input = r#"
+<!-- checklist = 'release' -->
+- [x] item 1
+    - [ ] item 1 #2
+- [ ] item 3 [OPTIONAL]
+        "#;
+    let checklist = Checklist::from_markdown(String::from(markdown_input)).unwrap();
+    let tap = checklist.to_report(ReportFormat::Tap).unwrap();
+    assert_eq!(
+        tap,
+        r#"TAP version 13
+1..3
+not ok 1 - item 1
+not ok 2 - > item 1 \#2
+ok 3 - item 3 [OPTIONAL] # SKIP optional item not resolved
+"#
+    );
+}
+
+#[test_log::test]
+fn parse_report_target() {
+    let target: ReportTarget = "json".parse().unwrap();
+    assert_eq!(target.format, ReportFormat::Json);
+    assert_eq!(target.path, None);
+
+    let target: ReportTarget = "json=out/report.json".parse().unwrap();
+    assert_eq!(target.format, ReportFormat::Json);
+    assert_eq!(target.path, Some("out/report.json".into()));
+
+    let target: ReportTarget = "junit=report.xml".parse().unwrap();
+    assert_eq!(target.format, ReportFormat::Junit);
+
+    let target: ReportTarget = "tap".parse().unwrap();
+    assert_eq!(target.format, ReportFormat::Tap);
+
+    assert!("yaml".parse::<ReportTarget>().is_err());
+}
+
+// Lint Tests
+#[test_log::test]
+fn lint_valid_checklist() {
+    let markdown_input = r#"
+<!-- checklist = 'name' -->
+- [ ] My checklist item
+- [ ] My optional checklist item [OPTIONAL]
+    - [ ] My nested checklist item
+<!-- checklist = 'name' -->
+        "#;
+    assert_eq!(lint(markdown_input), Vec::<Diagnostic>::new());
+}
+
+#[test_log::test]
+fn lint_reports_spec_violations() {
+    let markdown_input = r#"<!-- checklist = 'first' -->
+- [ ] item
+    - [ x] nested item
+- [ ] item
+<!-- checklist = 'second' -->
+<!-- checklist = 'second' -->
+<!-- checklist = 1 -->
+- [ ] item
+"#;
+    let messages: Vec<String> = lint(markdown_input)
+        .iter()
+        .map(|diagnostic| diagnostic.to_string())
+        .collect();
+    assert_eq!(
+        messages,
+        vec![
+            "3:5: malformed checkbox in \"[ x] nested item\", expected [ ] or [x]",
+            "4:1: duplicate item \"item\", first defined on line 2",
+            "5:1: checklist \"second\" has no items",
+            "7:1: invalid checklist delimiter, expected <!-- checklist = 'name' --> or <!-- checklist -->",
+        ]
+    );
+}
+
+#[test_log::test]
+fn lint_unbalanced_delimiters() {
+    let markdown_input = r#"<!-- checklist = 'a' -->
+- [ ] a item
+<!-- checklist = 'b' -->
+- [ ] b item
+<!-- checklist = 'a' -->
+<!-- checklist = 'c' -->
+- [ ] c item
+<!-- checklist = 'c' -->
+<!-- checklist -->
+"#;
+    let messages: Vec<String> = lint(markdown_input)
+        .iter()
+        .map(|diagnostic| diagnostic.to_string())
+        .collect();
+    assert_eq!(
+        messages,
+        vec![
+            "5:1: delimiter closes checklist \"a\", which isn't open",
+            "9:1: delimiter closes a checklist, but none is open",
+        ]
+    );
+}
+
+#[test_log::test]
+fn lint_duplicate_ids() {
+    let markdown_input = "<!-- checklist -->\n- [ ] first {#step}\n- [ ] second {#step}\n";
+    let messages: Vec<String> = lint(markdown_input)
+        .iter()
+        .map(|diagnostic| diagnostic.to_string())
+        .collect();
+    assert_eq!(
+        messages,
+        vec!["3:1: duplicate id \"step\", first defined on line 2"]
+    );
+}
+
+#[test_log::test]
+fn lint_no_checklist() {
+    let diagnostics = lint("# Just a heading\n- [ ] item\n");
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].message, "no checklist found");
+}
+
+#[test_log::test]
+fn from_markdown_item_run_command() {
+    let markdown_input = r#"
+<!-- checklist -->
+- [ ] commit is passing tests <!-- run: cargo test -->
+- [ ] no command <!-- run: -->
+        "#;
+    let checklist = Checklist::from_markdown(String::from(markdown_input)).unwrap();
+    assert_eq!(checklist.items[0].text, "commit is passing tests");
+    assert_eq!(checklist.items[0].run, Some("cargo test".to_string()));
+    assert_eq!(checklist.items[1].run, None);
+}
+
+#[test_log::test]
+fn run_checks_resolves_from_exit_status() {
+    let markdown_input = r#"
+<!-- checklist -->
+- [ ] passing <!-- run: echo output -->
+- [x] failing <!-- run: exit 1 -->
+- [ ] timing out <!-- run: sleep 5 -->
+- [x] manual
+        "#;
+    let mut checklist = Checklist::from_markdown(String::from(markdown_input)).unwrap();
+    checklist.run_checks(Duration::from_millis(200));
+    assert!(checklist.items[0].resolved);
+    assert!(!checklist.items[1].resolved);
+    assert!(!checklist.items[2].resolved);
+    assert!(checklist.items[3].resolved);
+    assert_eq!(checklist.get_count_unresolved(), 2);
+    let failed: Vec<bool> = checklist.items.iter().map(|item| item.run_failed).collect();
+    assert_eq!(failed, [false, true, true, false]);
+}
+
+#[cfg(unix)]
+#[test_log::test]
+fn run_checks_kills_what_a_timed_out_command_started() {
+    let marker = std::env::temp_dir().join(format!("kneeboard-timeout-{}", std::process::id()));
+    _ = std::fs::remove_file(&marker);
+    let markdown_input = format!(
+        "<!-- checklist -->\n- [ ] timing out <!-- run: (sleep 1; touch {}); true -->\n",
+        marker.display()
+    );
+    let mut checklist = Checklist::from_markdown(markdown_input).unwrap();
+    let started = std::time::Instant::now();
+    checklist.run_checks(Duration::from_millis(200));
+    assert!(started.elapsed() < Duration::from_secs(1));
+    assert!(!checklist.items[0].resolved);
+
+    std::thread::sleep(Duration::from_millis(1500));
+    assert!(
+        !marker.exists(),
+        "the command kept running after its timeout"
+    );
+}
+
+#[test_log::test]
+fn from_markdown_sequential_mode() {
+    let markdown_input = r#"
+<!-- checklist = 'deploy'
+mode = 'sequential' -->
+- [ ] drain the node
+- [ ] upgrade the node
+<!-- checklist = 'deploy' -->
+
+<!-- checklist = 'cleanup' -->
+- [ ] remove old images
+<!-- checklist = 'cleanup' -->
+        "#;
+    let checklists = Checklist::from_markdown_all(String::from(markdown_input)).unwrap();
+    assert_eq!(checklists.len(), 2);
+    assert_eq!(checklists[0].name, "deploy");
+    assert_eq!(checklists[0].mode, ChecklistMode::Sequential);
+    assert_eq!(checklists[0].items.len(), 2);
+    assert_eq!(checklists[1].mode, ChecklistMode::Free);
+
+    let loaded = Checklist::from_toml(checklists[0].to_toml().unwrap()).unwrap();
+    assert_eq!(loaded.mode, ChecklistMode::Sequential);
+    assert!(!checklists[1].to_toml().unwrap().contains("mode"));
+}
+
+#[test_log::test]
+fn lint_unknown_mode() {
+    let markdown_input = "<!-- checklist = 'deploy'\nmode = 'random' -->\n- [ ] drain the node\n";
+    assert_eq!(
+        lint(markdown_input),
+        vec![Diagnostic {
+            location: Location { line: 1, column: 1 },
+            message: String::from("unknown checklist mode \"random\", expected free or sequential"),
+        }]
+    );
+    let checklist = Checklist::from_markdown(String::from(markdown_input)).unwrap();
+    assert_eq!(checklist.mode, ChecklistMode::Free);
+}
+
+#[test_log::test]
+fn from_markdown_challenge_response() {
+    let markdown_input = r#"
+<!-- checklist -->
+- [ ] Flaps :: SET 15
+- [x] Transponder :: ALT {#transponder}
+- [ ] Check std::fs usage
+- [ ] Missing response ::
+- [ ] Landing light :: ON [OPTIONAL]
+- [ ] Taxi light [OPTIONAL] :: ON
+        "#;
+    let checklist = Checklist::from_markdown(String::from(markdown_input)).unwrap();
+    assert_eq!(checklist.items[0].text, "Flaps");
+    assert_eq!(checklist.items[0].response, Some("SET 15".to_string()));
+    assert_eq!(checklist.items[1].text, "Transponder");
+    assert_eq!(checklist.items[1].response, Some("ALT".to_string()));
+    assert_eq!(checklist.items[1].id, Some("transponder".to_string()));
+    assert_eq!(checklist.items[2].text, "Check std::fs usage");
+    assert_eq!(checklist.items[2].response, None);
+    assert_eq!(checklist.items[3].text, "Missing response ::");
+    assert_eq!(checklist.items[3].response, None);
+    assert!(!checklist.items[0].optional);
+    assert_eq!(checklist.items[4].text, "Landing light");
+    assert!(checklist.items[4].optional);
+    assert_eq!(checklist.items[5].response, Some("ON".to_string()));
+    assert!(checklist.items[5].optional);
+
+    let loaded = Checklist::from_toml(checklist.to_toml().unwrap()).unwrap();
+    assert_eq!(loaded.items[0].text, "Flaps");
+    assert_eq!(loaded.items[0].response, Some("SET 15".to_string()));
+}
+
+#[test_log::test]
+fn skips_are_recorded_until_the_item_is_resolved() {
+    let before = Checklist::from_markdown(String::from(
+        "<!-- checklist -->\n- [ ] drain the node\n- [ ] upgrade the node\n",
+    ))
+    .unwrap();
+    let mut checklist = before.clone();
+    checklist.items[0].skip("alice");
+    assert_eq!(checklist.items[0].skipped_by, Some(String::from("alice")));
+    assert!(checklist.items[0].skipped_at.is_some());
+    assert_eq!(
+        checklist.skips_since(&before),
+        vec![String::from("drain the node")]
+    );
+
+    let loaded = Checklist::from_toml(checklist.to_toml().unwrap()).unwrap();
+    assert_eq!(loaded.items[0].skipped_by, Some(String::from("alice")));
+    assert!(loaded.skips_since(&checklist).is_empty());
+
+    checklist.items[0].set_resolved(true, "bob");
+    assert_eq!(checklist.items[0].skipped_at, None);
+    assert_eq!(checklist.items[0].skipped_by, None);
+}
+
+#[test_log::test]
+fn merge_checklist_carries_notes() {
+    let mut saved_checklist = Checklist::from_markdown(String::from(
+        "<!-- checklist -->\n- [x] tag the release\n- [ ] update the changelog\n",
+    ))
+    .unwrap();
+    saved_checklist.items[0].note = Some(String::from("tagged as v1.2.0"));
+    saved_checklist.items[1].note = Some(String::from("see TICKET-42"));
+    let saved_checklist = Checklist::from_toml(saved_checklist.to_toml().unwrap()).unwrap();
+
+    let mut checklist = Checklist::from_markdown(String::from(
+        "<!-- checklist -->\n- [ ] tag the release\n- [ ] update the change log\n",
+    ))
+    .unwrap();
+    let report = checklist.merge_checklist(&saved_checklist);
+    assert!(report.unmatched.is_empty());
+    assert!(checklist.items[0].resolved);
+    assert_eq!(
+        checklist.items[0].note,
+        Some("tagged as v1.2.0".to_string())
+    );
+    assert_eq!(checklist.items[1].note, Some("see TICKET-42".to_string()));
+}
+
+#[test_log::test]
+fn run_record_round_trip() {
+    let before = Checklist::from_markdown(String::from(
+        "<!-- checklist = 'release' -->\n- [ ] tag the release\n- [x] update the changelog\n- [ ] publish\n",
+    ))
+    .unwrap();
+    let mut after = before.clone();
+    after.items[0].resolved = true;
+    after.items[1].resolved = false;
+
+    let record = RunRecord::new(&before, &after, String::from("alice"));
+    assert_eq!(record.checklist, "release");
+    assert_eq!(record.unresolved, 2);
+    assert_eq!(
+        record
+            .changes
+            .iter()
+            .map(|change| (change.text.as_str(), change.resolved))
+            .collect::<Vec<_>>(),
+        vec![("tag the release", true), ("update the changelog", false)]
+    );
+
+    // Records are appended one after the other to the history file
+    let history_file = format!(
+        "{}\n{}",
+        record.to_toml().unwrap(),
+        record.to_toml().unwrap()
+    );
+    let history = History::from_toml(history_file).unwrap();
+    assert_eq!(history.runs, vec![record.clone(), record]);
+}
+
+#[test_log::test]
+fn stamp_resolutions_records_who_and_when() {
+    let before = Checklist::from_markdown(String::from(
+        "<!-- checklist -->\n- [ ] tag the release\n- [x] update the changelog\n- [x] publish\n",
+    ))
+    .unwrap();
+    let mut after = before.clone();
+    after.items[0].resolved = true;
+    after.items[2].resolved = false;
+    after.items[2].resolved_by = Some(String::from("bob"));
+
+    after.stamp_resolutions(&before, "alice");
+    assert_eq!(after.items[0].resolved_by, Some("alice".to_string()));
+    assert!(after.items[0].resolved_at.is_some());
+    assert_eq!(after.items[1].resolved_by, None);
+    assert_eq!(after.items[2].resolved_by, None);
+
+    let loaded = Checklist::from_toml(after.to_toml().unwrap()).unwrap();
+    assert_eq!(loaded.items[0].resolved_at, after.items[0].resolved_at);
+    assert_eq!(loaded.items[0].resolved_by, Some("alice".to_string()));
+    assert!(
+        loaded.items[0]
+            .resolution()
+            .unwrap()
+            .starts_with("alice at ")
+    );
+
+    // Stamps carry over from the save file and are dropped when an item is unticked
+    let mut checklist = before.clone();
+    checklist.merge_checklist(&loaded);
+    assert_eq!(checklist.items[0].resolved_by, Some("alice".to_string()));
+    checklist.items[0].set_resolved(false, "bob");
+    assert_eq!(checklist.items[0].resolved_at, None);
+    assert_eq!(checklist.items[0].resolved_by, None);
+    checklist.items[0].set_resolved(true, "bob");
+    assert_eq!(checklist.items[0].resolved_by, Some("bob".to_string()));
+}
+
+#[test_log::test]
+fn from_markdown_expiry_rules() {
+    let markdown_input = r#"
+<!-- checklist = 'release'
+expires = '24h'
+reset = 'on-new-commit' -->
+- [ ] tag the release
+- [ ] check the dashboards <!-- reset: daily -->
+- [ ] rotate the keys <!-- expires: 7d -->
+- [ ] bad rule <!-- expires: soon -->
+<!-- checklist = 'release' -->
+        "#;
+    let checklist = Checklist::from_markdown(String::from(markdown_input)).unwrap();
+    assert_eq!(checklist.name, "release");
+    assert_eq!(
+        checklist.expiry,
+        Expiry {
+            expires: Some(time::Duration::hours(24)),
+            reset: Some(Reset::OnNewCommit),
+        }
+    );
+    assert_eq!(checklist.items[0].expiry, Expiry::default());
+    assert_eq!(checklist.items[1].expiry.reset, Some(Reset::Daily));
+    assert_eq!(
+        checklist.items[2].expiry.expires,
+        Some(time::Duration::days(7))
+    );
+    assert_eq!(checklist.items[3].expiry, Expiry::default());
+}
+
+#[test_log::test]
+fn parse_durations() {
+    assert_eq!(parse_duration("30s"), Ok(time::Duration::seconds(30)));
+    assert_eq!(parse_duration("15m"), Ok(time::Duration::minutes(15)));
+    assert_eq!(parse_duration("24h"), Ok(time::Duration::hours(24)));
+    assert_eq!(parse_duration("2w"), Ok(time::Duration::weeks(2)));
+    assert!(parse_duration("h").is_err());
+    assert!(parse_duration("24").is_err());
+    assert!(parse_duration("1 day").is_err());
+}
+
+#[test_log::test]
+fn expire_resolutions_unresolves_old_items() {
+    let markdown_input = r#"
+<!-- checklist = 'release'
+expires = '24h' -->
+- [ ] resolved an hour ago
+- [ ] resolved two days ago
+- [ ] resolved yesterday <!-- reset: daily -->
+- [ ] resolved on the previous commit <!-- reset: on-new-commit -->
+- [x] resolved in the Markdown
+<!-- checklist = 'release' -->
+        "#;
+    let now = time::OffsetDateTime::parse(
+        "2026-10-18T12:00:00Z",
+        &time::format_description::well_known::Rfc3339,
+    )
+    .unwrap();
+    let mut checklist = Checklist::from_markdown(String::from(markdown_input)).unwrap();
+    checklist.commit = Some(String::from("old"));
+    let resolved_at = [
+        now - time::Duration::hours(1),
+        now - time::Duration::days(2),
+        now - time::Duration::hours(13),
+        now - time::Duration::hours(1),
+    ];
+    for (item, resolved_at) in checklist.items.iter_mut().zip(resolved_at) {
+        item.resolved = true;
+        item.resolved_at = Some(resolved_at);
+        item.resolved_by = Some(String::from("alice"));
+    }
+
+    let expired = checklist.expire_resolutions(Some("new"), now);
+    assert_eq!(
+        expired,
+        vec![
+            "resolved two days ago".to_string(),
+            "resolved yesterday".to_string(),
+            "resolved on the previous commit".to_string(),
+        ]
+    );
+    assert!(checklist.items[0].resolved);
+    assert!(!checklist.items[1].resolved);
+    assert_eq!(checklist.items[1].resolved_by, None);
+    assert!(checklist.items[4].resolved);
+
+    checklist.items[3].resolved = true;
+    checklist.items[3].resolved_at = Some(now);
+    checklist.commit = Some(String::from("new"));
+    assert!(checklist.expire_resolutions(Some("new"), now).is_empty());
+}
+
+#[test_log::test]
+fn daily_reset_follows_the_local_date() {
+    let markdown_input = r#"
+<!-- checklist = 'on-call'
+reset = 'daily' -->
+- [ ] resolved last night
+- [ ] resolved this morning
+<!-- checklist = 'on-call' -->
+        "#;
+    let parse = |input: &str| {
+        time::OffsetDateTime::parse(input, &time::format_description::well_known::Rfc3339).unwrap()
+    };
+    // The same UTC day, but midnight has passed in between in UTC-8
+    let now = parse("2026-10-18T09:00:00-08:00");
+    let resolved_at = [parse("2026-10-18T07:00:00Z"), parse("2026-10-18T08:30:00Z")];
+    let mut checklist = Checklist::from_markdown(String::from(markdown_input)).unwrap();
+    for (item, resolved_at) in checklist.items.iter_mut().zip(resolved_at) {
+        item.resolved = true;
+        item.resolved_at = Some(resolved_at);
+    }
+    assert_eq!(
+        checklist.expire_resolutions(None, now),
+        vec!["resolved last night".to_string()]
+    );
+}
+
+#[test_log::test]
+fn lint_invalid_expiry() {
+    let markdown_input =
+        "<!-- checklist = 'release'\nreset = 'weekly' -->\n- [ ] tag the release\n";
+    assert_eq!(
+        lint(markdown_input),
+        vec![Diagnostic {
+            location: Location { line: 1, column: 1 },
+            message: String::from("unknown reset \"weekly\", expected daily or on-new-commit"),
+        }]
+    );
+}
+
+#[test_log::test]
+fn from_markdown_key() {
+    let markdown_input =
+        "<!-- checklist = 'release'\nkey = 'team-release' -->\n- [ ] tag the release\n";
+    let checklist = Checklist::from_markdown(String::from(markdown_input)).unwrap();
+    assert_eq!(checklist.key.as_deref(), Some("team-release"));
+    assert!(!checklist.to_toml().unwrap().contains("team-release"));
+
+    let markdown_input = "<!-- checklist = 'release' -->\n- [ ] tag the release\n";
+    let checklist = Checklist::from_markdown(String::from(markdown_input)).unwrap();
+    assert_eq!(checklist.key, None);
+}
+
+#[test_log::test]
+fn lint_invalid_key() {
+    let markdown_input = "<!-- checklist = 'release'\nkey = 1 -->\n- [ ] tag the release\n";
+    assert_eq!(
+        lint(markdown_input),
+        vec![Diagnostic {
+            location: Location { line: 1, column: 1 },
+            message: String::from("checklist key 1 is not a non-empty string"),
+        }]
+    );
+}
+
+#[test_log::test]
+fn select_items_by_text_id_number_glob_and_prefix() {
+    let markdown_input = r#"
+<!-- checklist -->
+- [ ] migrations applied {#migrations}
+- [ ] deploy the web servers
+    - [ ] deploy the workers
+- [ ] 2
+        "#;
+    let checklist = Checklist::from_markdown(String::from(markdown_input)).unwrap();
+    assert_eq!(checklist.select_items("migrations").unwrap(), vec![0]);
+    assert_eq!(
+        checklist.select_items("migrations applied").unwrap(),
+        vec![0]
+    );
+    assert_eq!(checklist.select_items("2").unwrap(), vec![3]);
+    assert_eq!(checklist.select_items("3").unwrap(), vec![2]);
+    assert_eq!(checklist.select_items("deploy *").unwrap(), vec![1, 2]);
+    assert_eq!(
+        checklist.select_items("deploy the w?rkers").unwrap(),
+        vec![2]
+    );
+    assert_eq!(checklist.select_items("migr").unwrap(), vec![0]);
+    assert!(checklist.select_items("deploy").is_err());
+    assert!(checklist.select_items("5").is_err());
+    assert!(checklist.select_items("rollback").is_err());
+}
+
+#[test_log::test]
+fn glob_match_wildcards() {
+    assert!(glob_match("*", ""));
+    assert!(glob_match("a*c", "abbbc"));
+    assert!(glob_match("*tests*", "commit is passing tests"));
+    assert!(glob_match("a?c", "abc"));
+    assert!(!glob_match("a?c", "ac"));
+    assert!(!glob_match("a*d", "abc"));
+    assert!(glob_match("*a*a", "banana"));
+}
+
+#[test_log::test]
+fn trailer_counts_required_items() {
+    let markdown_input = r#"
+<!-- checklist = 'release' -->
+- [x] tag the release
+- [ ] publish the crate
+    - [x] build the docs
+- [ ] announce it [OPTIONAL]
+- [x] update the changelog [OPTIONAL]
+<!-- checklist = 'release' -->
+        "#;
+    let checklist = Checklist::from_markdown(String::from(markdown_input)).unwrap();
+    let trailer = checklist.to_trailer();
+    assert_eq!(trailer.to_string(), "release 2/3");
+    assert!(!trailer.is_complete());
+    assert_eq!(checklist.skipped_items(), vec!["announce it [OPTIONAL]"]);
+}
+
+#[test_log::test]
+fn trailer_from_str() {
+    let trailer: ChecklistTrailer = "Test Checklist 1 7/7".parse().unwrap();
+    assert_eq!(
+        trailer,
+        ChecklistTrailer {
+            name: String::from("Test Checklist 1"),
+            resolved: 7,
+            required: 7,
+        }
+    );
+    assert!(trailer.is_complete());
+    assert_eq!(
+        "release".parse::<ChecklistTrailer>(),
+        Err(String::from(
+            "\"release\" is not a checklist name followed by resolved/required"
+        ))
+    );
+    assert!("release 2/x".parse::<ChecklistTrailer>().is_err());
+}
+
+#[test_log::test]
+fn saved_scope_round_trips_through_toml() {
+    let mut checklist =
+        Checklist::from_markdown(String::from("<!-- checklist -->\n- [ ] run the tests\n"))
+            .unwrap();
+    assert!(!checklist.to_toml().unwrap().contains("[scope]"));
+
+    let scope = SavedScope {
+        repository: String::from("/src/kneeboard/.git"),
+        branch: Some(String::from("refs/heads/feature")),
+        worktree: None,
+    };
+    checklist.scope = Some(scope.clone());
+    let toml_string = checklist.to_toml().unwrap();
+    assert!(toml_string.contains("[scope]"));
+    assert!(!toml_string.contains("worktree"));
+    let loaded = Checklist::from_toml(toml_string).unwrap();
+    assert_eq!(loaded.scope, Some(scope));
+}
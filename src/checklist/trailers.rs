@@ -0,0 +1,71 @@
+use super::model::Checklist;
+use std::{fmt, str::FromStr};
+
+/// Key of the trailer recording how much of a checklist was done
+pub const CHECKLIST_TRAILER: &str = "Checklist";
+/// Key of the trailers naming the optional items left unresolved
+pub const SKIPPED_TRAILER: &str = "Checklist-Skipped";
+
+/// The value of a `Checklist:` trailer, `<name> <resolved>/<required>` over the required items
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChecklistTrailer {
+    pub name: String,
+    pub resolved: usize,
+    pub required: usize,
+}
+
+impl ChecklistTrailer {
+    /// Every required item was resolved
+    pub fn is_complete(&self) -> bool {
+        self.resolved >= self.required
+    }
+}
+
+impl fmt::Display for ChecklistTrailer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}/{}", self.name, self.resolved, self.required)
+    }
+}
+
+impl FromStr for ChecklistTrailer {
+    type Err = String;
+
+    fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || format!("{input:?} is not a checklist name followed by resolved/required");
+        let (name, counts) = input.trim().rsplit_once(' ').ok_or_else(invalid)?;
+        let (resolved, required) = counts.split_once('/').ok_or_else(invalid)?;
+        Ok(ChecklistTrailer {
+            name: name.trim().to_string(),
+            resolved: resolved.parse().map_err(|_| invalid())?,
+            required: required.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+impl Checklist {
+    /// The `Checklist:` trailer of the checklist as it is now
+    pub fn to_trailer(&self) -> ChecklistTrailer {
+        let required: Vec<_> = self
+            .walk()
+            .into_iter()
+            .filter(|(_, item)| !item.optional)
+            .collect();
+        ChecklistTrailer {
+            name: self.name.clone(),
+            resolved: required
+                .iter()
+                .filter(|(_, item)| item.is_resolved())
+                .count(),
+            required: required.len(),
+        }
+    }
+
+    /// The text of every optional item left unresolved, one `Checklist-Skipped:` trailer each
+    pub fn skipped_items(&self) -> Vec<String> {
+        self.walk()
+            .into_iter()
+            .filter(|(_, item)| item.optional && !item.is_resolved())
+            .map(|(_, item)| item.text.clone())
+            .collect()
+    }
+}
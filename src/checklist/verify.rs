@@ -0,0 +1,150 @@
+use super::model::Checklist;
+use log::{debug, info, warn};
+use std::io::Read;
+use std::process::{Child, Command, Stdio};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// The outcome of running an item's `<!-- run: command -->`
+struct CommandOutcome {
+    success: bool,
+    timed_out: bool,
+    /// Combined stdout and stderr of the command
+    output: String,
+}
+
+impl Checklist {
+    /// Runs the command of every item that has one, resolving the item when it exits successfully.
+    /// Commands run through the shell in the current directory and are killed after `timeout`
+    pub fn run_checks(&mut self, timeout: Duration) {
+        self.walk_mut(|_, item| {
+            let Some(command) = &item.run else {
+                return;
+            };
+            info!("[run_checks] Running {command:?} for {:?}", item.text);
+            let result = run_command(command, timeout);
+            if result.success {
+                info!("[run_checks] {:?} passed", item.text);
+            } else if result.timed_out {
+                warn!(
+                    "[run_checks] {:?} timed out after {}s running {command:?}",
+                    item.text,
+                    timeout.as_secs()
+                );
+            } else {
+                warn!(
+                    "[run_checks] {:?} failed running {command:?}:\n{}",
+                    item.text, result.output
+                );
+            }
+            item.resolved = result.success;
+            item.run_failed = !result.success;
+        });
+    }
+}
+
+fn shell(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C").arg(command);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c").arg(command);
+        // A group of its own lets a timeout kill whatever the command started along with it
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut shell, 0);
+        shell
+    }
+}
+
+/// Kills the child along with every process in its group
+fn kill(child: &mut Child) {
+    #[cfg(unix)]
+    {
+        let group = format!("-{}", child.id());
+        if let Err(error) = Command::new("kill")
+            .args(["-KILL", "--", &group])
+            .stderr(Stdio::null())
+            .status()
+        {
+            warn!("[kill] Failed to kill the process group of the command: {error}");
+        }
+    }
+    _ = child.kill();
+    _ = child.wait();
+}
+
+fn run_command(command: &str, timeout: Duration) -> CommandOutcome {
+    let mut result = CommandOutcome {
+        success: false,
+        timed_out: false,
+        output: String::new(),
+    };
+
+    let mut child = match shell(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(error) => {
+            result.output = format!("failed to start command: {error}");
+            return result;
+        }
+    };
+    // Pipes are drained on their own threads so a chatty command can't block on a full pipe
+    let stdout = capture(child.stdout.take());
+    let stderr = capture(child.stderr.take());
+
+    let started = Instant::now();
+    match wait_with_timeout(&mut child, timeout) {
+        Some(success) => {
+            result.success = success;
+            for reader in [stdout, stderr] {
+                result.output.push_str(&reader.join().unwrap_or_default());
+            }
+        }
+        None => {
+            // Readers are left behind as processes started by the command may keep the pipes open
+            result.timed_out = true;
+            result.output = String::from("command timed out");
+        }
+    }
+    debug!(
+        "[run_command] {command:?} finished in {:?} with output {:?}",
+        started.elapsed(),
+        result.output
+    );
+    result
+}
+
+fn capture(pipe: Option<impl Read + Send + 'static>) -> JoinHandle<String> {
+    thread::spawn(move || {
+        let mut output = String::new();
+        if let Some(mut pipe) = pipe {
+            _ = pipe.read_to_string(&mut output);
+        }
+        output
+    })
+}
+
+/// Waits for the child to exit, returning whether it succeeded or `None` once it was killed
+fn wait_with_timeout(child: &mut Child, timeout: Duration) -> Option<bool> {
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return Some(status.success()),
+            Ok(None) if started.elapsed() >= timeout => {
+                kill(child);
+                return None;
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(20)),
+            Err(error) => {
+                warn!("[wait_with_timeout] Failed to wait for command: {error}");
+                return Some(false);
+            }
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use crate::checklist::{Checklist, ChecklistItem, ChecklistMode, RunRecord};
+use crate::error::{KneeboardError, Result};
+use crate::helpers::git::{current_user, git};
+use crate::save::Store;
+use std::collections::BTreeSet;
+use std::process::ExitCode;
+
+/// Resolves, or unresolves, the items picked by `selectors` in the saved progress. Items of a
+/// sequential checklist are resolved in order, open items before them fail the check unless
+/// they are to be `skip`ped
+pub fn run(
+    store: &Store,
+    checklist_path: &String,
+    name: Option<&str>,
+    selectors: &[String],
+    resolved: bool,
+    skip: bool,
+) -> Result<ExitCode> {
+    let mut checklist = Checklist::load(checklist_path, name)?;
+    let head = git(&["rev-parse", "HEAD"]);
+    store.load_progress(&mut checklist, checklist_path, head.as_deref())?;
+
+    let mut selected = BTreeSet::new();
+    for selector in selectors {
+        selected.extend(checklist.select_items(selector)?);
+    }
+
+    let checklist_before = checklist.clone();
+    let user = current_user();
+    if resolved && checklist.mode == ChecklistMode::Sequential {
+        skip_open_items_before(&mut checklist, &selected, skip, &user)?;
+    }
+    let action = if resolved { "Checked" } else { "Unchecked" };
+    let mut index = 0;
+    checklist.walk_mut(|_, item| {
+        if selected.contains(&index) {
+            item.set_resolved(resolved, &user);
+            println!("{action} {:?}", item.text);
+        }
+        index += 1;
+    });
+
+    checklist.commit = head;
+    store.save_checklist(&checklist, checklist_path)?;
+    let record = RunRecord::new(&checklist_before, &checklist, user);
+    store.append_history(&record, checklist_path, &checklist)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Skips the items of a sequential checklist that are neither resolved, skipped nor picked and
+/// come before the last item picked. Fails without `skip` when there are any
+fn skip_open_items_before(
+    checklist: &mut Checklist,
+    selected: &BTreeSet<usize>,
+    skip: bool,
+    user: &str,
+) -> Result<()> {
+    let Some(&last) = selected.last() else {
+        return Ok(());
+    };
+    let is_open = |index: usize, item: &ChecklistItem| {
+        index < last && !selected.contains(&index) && !item.resolved && item.skipped_at.is_none()
+    };
+    let open: Vec<String> = checklist
+        .walk()
+        .into_iter()
+        .enumerate()
+        .filter(|(index, (_, item))| is_open(*index, item))
+        .map(|(_, (_, item))| format!("{:?}", item.text))
+        .collect();
+    if open.is_empty() {
+        return Ok(());
+    }
+    if !skip {
+        return Err(KneeboardError::validation(format!(
+            "open items come first in {:?}: {}, pass --skip to skip them out of order",
+            checklist.name,
+            open.join(", ")
+        )));
+    }
+    let mut index = 0;
+    checklist.walk_mut(|_, item| {
+        if is_open(index, item) {
+            item.skip(user);
+            println!("Skipped {:?} out of order", item.text);
+        }
+        index += 1;
+    });
+    Ok(())
+}
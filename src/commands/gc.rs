@@ -0,0 +1,15 @@
+use crate::error::Result;
+use crate::save::Store;
+use std::process::ExitCode;
+
+/// Removes the progress saved for branches and worktrees that no longer exist
+pub fn run(store: &Store, checklist_path: &String) -> Result<ExitCode> {
+    let removed = store.collect_garbage(checklist_path)?;
+    if removed.is_empty() {
+        println!("Nothing to collect");
+    }
+    for scope in removed {
+        println!("Removed the saved progress of {scope}");
+    }
+    Ok(ExitCode::SUCCESS)
+}
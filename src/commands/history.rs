@@ -0,0 +1,59 @@
+use crate::checklist::Checklist;
+use crate::error::{KneeboardError, Result};
+use crate::save::Store;
+use std::process::ExitCode;
+
+/// Lists the recorded runs of the checklist, or shows the changes made in run number `run`
+pub fn run(
+    store: &Store,
+    checklist_path: &String,
+    name: Option<&str>,
+    run: Option<usize>,
+) -> Result<ExitCode> {
+    let checklist = Checklist::load(checklist_path, name)?;
+    let history = store.load_history(checklist_path, &checklist)?;
+
+    let Some(number) = run else {
+        if history.runs.is_empty() {
+            println!("No runs recorded for {:?}", checklist.name);
+        }
+        for (index, record) in history.runs.iter().enumerate() {
+            println!(
+                "{}  {}  {}  {} changed  {} unresolved",
+                index + 1,
+                record.formatted_timestamp(),
+                record.user,
+                record.changes.len(),
+                record.unresolved
+            );
+        }
+        return Ok(ExitCode::SUCCESS);
+    };
+
+    let record = number
+        .checked_sub(1)
+        .and_then(|index| history.runs.get(index))
+        .ok_or_else(|| {
+            KneeboardError::validation(format!(
+                "no run {number} recorded for {:?}, it has {} runs",
+                checklist.name,
+                history.runs.len()
+            ))
+        })?;
+    println!("Run {number} of {:?}", record.checklist);
+    println!("Time: {}", record.formatted_timestamp());
+    println!("User: {}", record.user);
+    println!("Unresolved: {}", record.unresolved);
+    println!("Changed:");
+    for change in &record.changes {
+        let marker = if change.resolved { "x" } else { " " };
+        println!("  [{marker}] {}", change.text);
+    }
+    if !record.skipped.is_empty() {
+        println!("Skipped out of order:");
+    }
+    for text in &record.skipped {
+        println!("  {text}");
+    }
+    Ok(ExitCode::SUCCESS)
+}
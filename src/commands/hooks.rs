@@ -0,0 +1,252 @@
+use crate::config::Config;
+use crate::error::{KneeboardError, Result};
+use crate::helpers::git::{git, repo_relative_path};
+use clap::ValueEnum;
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+/// First line after the shebang of every hook kneeboard writes, tells its hooks apart from others
+const HOOK_MARKER: &str = "# Installed by kneeboard";
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Hook {
+    PreCommit,
+    CommitMsg,
+    PrePush,
+    PrepareCommitMsg,
+}
+
+impl Hook {
+    pub const ALL: [Hook; 4] = [
+        Hook::PreCommit,
+        Hook::CommitMsg,
+        Hook::PrePush,
+        Hook::PrepareCommitMsg,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Hook::PreCommit => "pre-commit",
+            Hook::CommitMsg => "commit-msg",
+            Hook::PrePush => "pre-push",
+            Hook::PrepareCommitMsg => "prepare-commit-msg",
+        }
+    }
+}
+
+impl fmt::Display for Hook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// The hook a kneeboard hook replaced, run before the checklist
+fn chained_path(hook_path: &Path) -> PathBuf {
+    hook_path.with_extension("before-kneeboard")
+}
+
+/// The hooks directory of the current repository, `core.hooksPath` when it is set
+fn hooks_dir() -> Result<PathBuf> {
+    git(&["rev-parse", "--git-path", "hooks"])
+        .map(PathBuf::from)
+        .ok_or_else(|| KneeboardError::config("hooks can only be managed inside a git repository"))
+}
+
+fn is_kneeboard_hook(hook_path: &Path) -> bool {
+    fs::read_to_string(hook_path).is_ok_and(|script| script.contains(HOOK_MARKER))
+}
+
+/// Quotes `arg` for `sh`
+fn shell_quote(arg: &str) -> String {
+    if !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=".contains(c))
+    {
+        return arg.to_string();
+    }
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+/// The kneeboard arguments a hook runs with. A checklist or profile given to `install` wins,
+/// then a profile named after the hook, then the default profile
+fn hook_args(
+    hook: Hook,
+    config: &Config,
+    profile: Option<&str>,
+    checklist_path: Option<&str>,
+    name: Option<&str>,
+) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+    if let Some(profile) = profile {
+        args.extend([String::from("--profile"), profile.to_string()]);
+    }
+    if let Some(checklist_path) = checklist_path {
+        args.extend([
+            String::from("--checklist-path"),
+            // Git runs hooks from the root of the repository
+            repo_relative_path(checklist_path)
+                .map_err(|error| KneeboardError::io(checklist_path, error))?,
+        ]);
+    }
+    if let Some(name) = name {
+        args.extend([String::from("--name"), name.to_string()]);
+    }
+    if !args.is_empty() {
+        return Ok(args);
+    }
+    if config.profiles.contains_key(hook.name()) {
+        return Ok(vec![String::from("--profile"), hook.name().to_string()]);
+    }
+    if config.profile(None)?.is_some() {
+        return Ok(args);
+    }
+    Err(KneeboardError::config(format!(
+        "no checklist for the {hook} hook, pass --checklist-path or --profile, or add a {hook} or default profile"
+    )))
+}
+
+/// Commit message hooks add trailers for the saved progress, the others run the checklist.
+/// Hooks that `save` drop progress from earlier commits so every commit is checked again
+fn hook_script(hook: Hook, args: &[String], save: bool) -> String {
+    let message_hook = matches!(hook, Hook::CommitMsg | Hook::PrepareCommitMsg);
+    let mut command = vec![String::from("kneeboard")];
+    if save {
+        command.extend(["--reset-rule", "on-new-commit"].map(String::from));
+    }
+    if message_hook {
+        command.push(String::from("trailers"));
+    } else if save {
+        command.push(String::from("--save"));
+    }
+    command.extend(args.iter().map(|arg| shell_quote(arg)));
+    if message_hook {
+        command.push(String::from(r#""$1""#));
+    }
+    let command = command.join(" ");
+    format!(
+        r#"#!/bin/sh
+{HOOK_MARKER}, `kneeboard hooks uninstall` puts back the hook it replaced
+chained="$(dirname "$0")/{hook}.before-kneeboard"
+if [ -x "$chained" ]; then
+    "$chained" "$@" || exit $?
+fi
+# Git hands some hooks input on stdin, the checklist is answered on the terminal
+if (exec < /dev/tty) 2>/dev/null; then
+    exec < /dev/tty
+fi
+exec {command}
+"#
+    )
+}
+
+/// The command a kneeboard hook runs
+fn hook_command(script: &str) -> Option<&str> {
+    script
+        .lines()
+        .rev()
+        .find_map(|line| line.strip_prefix("exec kneeboard"))
+        .map(|command| command.trim_start_matches(' '))
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+        .map_err(|error| KneeboardError::io(path, error))
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+/// Writes hooks running the checklist, a hook that was already there is kept and run first
+pub fn install(
+    config: &Config,
+    hooks: &[Hook],
+    save: bool,
+    profile: Option<&str>,
+    checklist_path: Option<&str>,
+    name: Option<&str>,
+) -> Result<ExitCode> {
+    let hooks_dir = hooks_dir()?;
+    fs::create_dir_all(&hooks_dir).map_err(|error| KneeboardError::io(&hooks_dir, error))?;
+    let hooks = if hooks.is_empty() {
+        &[Hook::PreCommit][..]
+    } else {
+        hooks
+    };
+    for &hook in hooks {
+        let args = hook_args(hook, config, profile, checklist_path, name)?;
+        let hook_path = hooks_dir.join(hook.name());
+        if hook_path.exists() && !is_kneeboard_hook(&hook_path) {
+            let chained = chained_path(&hook_path);
+            if chained.exists() {
+                return Err(KneeboardError::config(format!(
+                    "{} already exists, move it before installing the {hook} hook",
+                    chained.display()
+                )));
+            }
+            fs::rename(&hook_path, &chained)
+                .map_err(|error| KneeboardError::io(&hook_path, error))?;
+            println!("Chained the existing {hook} hook");
+        }
+        fs::write(&hook_path, hook_script(hook, &args, save))
+            .map_err(|error| KneeboardError::io(&hook_path, error))?;
+        make_executable(&hook_path)?;
+        println!("Installed {hook} hook to {}", hook_path.display());
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Removes the hooks kneeboard installed and puts back the hooks they replaced
+pub fn uninstall(hooks: &[Hook]) -> Result<ExitCode> {
+    let hooks_dir = hooks_dir()?;
+    let hooks = if hooks.is_empty() { &Hook::ALL } else { hooks };
+    for &hook in hooks {
+        let hook_path = hooks_dir.join(hook.name());
+        if !is_kneeboard_hook(&hook_path) {
+            if hook_path.exists() {
+                println!("The {hook} hook wasn't installed by kneeboard, leaving it");
+            }
+            continue;
+        }
+        fs::remove_file(&hook_path).map_err(|error| KneeboardError::io(&hook_path, error))?;
+        let chained = chained_path(&hook_path);
+        if chained.exists() {
+            fs::rename(&chained, &hook_path)
+                .map_err(|error| KneeboardError::io(&chained, error))?;
+            println!("Uninstalled {hook} hook and put back the hook it replaced");
+        } else {
+            println!("Uninstalled {hook} hook");
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Prints every hook kneeboard knows about and what runs it
+pub fn list() -> Result<ExitCode> {
+    let hooks_dir = hooks_dir()?;
+    println!("Hooks in {}:", hooks_dir.display());
+    for hook in Hook::ALL {
+        let hook_path = hooks_dir.join(hook.name());
+        let status = match fs::read_to_string(&hook_path) {
+            Ok(script) if script.contains(HOOK_MARKER) => {
+                let command = hook_command(&script).unwrap_or_default();
+                let mut status = format!("kneeboard {command}").trim_end().to_string();
+                if chained_path(&hook_path).exists() {
+                    status.push_str(", after the hook it replaced");
+                }
+                status
+            }
+            Ok(_) => String::from("other hook"),
+            Err(_) => String::from("not installed"),
+        };
+        println!("  {:<18}  {status}", hook.name());
+    }
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,29 @@
+use crate::checklist::lint;
+use log::{error, info};
+use std::{fs, process::ExitCode};
+
+/// Lints every file, printing `path:line:column: message` for each problem found.
+/// Exits with the number of problems found
+pub fn run(paths: &[String]) -> ExitCode {
+    let mut problem_count: usize = 0;
+    for path in paths {
+        let file_contents = match fs::read_to_string(path) {
+            Ok(file_contents) => file_contents,
+            Err(error) => {
+                error!("Failed to read {path}: {error}");
+                println!("{path}: failed to read file: {error}");
+                problem_count += 1;
+                continue;
+            }
+        };
+        let diagnostics = lint(&file_contents);
+        if diagnostics.is_empty() {
+            info!("No problems found in {path}");
+        }
+        for diagnostic in &diagnostics {
+            println!("{path}:{diagnostic}");
+        }
+        problem_count += diagnostics.len();
+    }
+    ExitCode::from(std::cmp::min(problem_count, 255) as u8)
+}
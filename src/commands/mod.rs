@@ -0,0 +1,9 @@
+pub mod check;
+pub mod gc;
+pub mod history;
+pub mod hooks;
+pub mod lint;
+pub mod reset;
+pub mod status;
+pub mod trailers;
+pub mod verify_commit;
@@ -0,0 +1,47 @@
+use crate::checklist::{Checklist, RunRecord};
+use crate::error::Result;
+use crate::helpers::git::{current_user, git};
+use crate::save::Store;
+use log::info;
+use std::collections::BTreeSet;
+use std::process::ExitCode;
+
+/// Clears the saved progress of the items picked by `selectors`, or of every item when there are none
+pub fn run(
+    store: &Store,
+    checklist_path: &String,
+    name: Option<&str>,
+    selectors: &[String],
+) -> Result<ExitCode> {
+    let mut checklist = Checklist::load(checklist_path, name)?;
+    let head = git(&["rev-parse", "HEAD"]);
+    if !store.load_progress(&mut checklist, checklist_path, head.as_deref())? {
+        println!("No progress saved for {:?}", checklist.name);
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let mut selected = BTreeSet::new();
+    for selector in selectors {
+        selected.extend(checklist.select_items(selector)?);
+    }
+
+    let checklist_before = checklist.clone();
+    let user = current_user();
+    let mut reset_count = 0;
+    let mut index = 0;
+    checklist.walk_mut(|_, item| {
+        if selectors.is_empty() || selected.contains(&index) {
+            info!("Resetting {:?}", item.text);
+            reset_count += usize::from(item.resolved);
+            item.set_resolved(false, &user);
+        }
+        index += 1;
+    });
+
+    checklist.commit = head;
+    store.save_checklist(&checklist, checklist_path)?;
+    let record = RunRecord::new(&checklist_before, &checklist, user);
+    store.append_history(&record, checklist_path, &checklist)?;
+    println!("Reset {reset_count} items of {:?}", checklist.name);
+    Ok(ExitCode::SUCCESS)
+}
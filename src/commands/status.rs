@@ -0,0 +1,37 @@
+use crate::checklist::Checklist;
+use crate::error::Result;
+use crate::helpers::git::git;
+use crate::save::Store;
+use std::process::ExitCode;
+
+/// Prints the saved progress of the checklist along with its outstanding items.
+/// Exits with the number of unresolved items, like a headless run
+pub fn run(store: &Store, checklist_path: &String, name: Option<&str>) -> Result<ExitCode> {
+    let mut checklist = Checklist::load(checklist_path, name)?;
+    let head = git(&["rev-parse", "HEAD"]);
+    if !store.load_progress(&mut checklist, checklist_path, head.as_deref())? {
+        println!("No progress saved for {:?}", checklist.name);
+    }
+
+    let totals = checklist.get_totals();
+    println!(
+        "{}: {}/{} resolved, {} unresolved",
+        checklist.name, totals.resolved, totals.total, totals.unresolved
+    );
+    let outstanding: Vec<_> = checklist
+        .walk()
+        .into_iter()
+        .filter(|(_, item)| !item.is_resolved())
+        .collect();
+    if !outstanding.is_empty() {
+        println!("Outstanding:");
+    }
+    for (depth, item) in outstanding {
+        let optional = if item.optional { " (optional)" } else { "" };
+        println!("{}  [ ] {}{optional}", "    ".repeat(depth), item.text);
+    }
+
+    Ok(ExitCode::from(
+        std::cmp::min(checklist.get_count_unresolved(), 255) as u8,
+    ))
+}
@@ -0,0 +1,77 @@
+use crate::checklist::{CHECKLIST_TRAILER, Checklist, SKIPPED_TRAILER};
+use crate::error::{KneeboardError, Result};
+use crate::helpers::git::{git, git_succeeds};
+use crate::save::Store;
+use log::{info, warn};
+use std::{fs, io, path::Path, process::ExitCode};
+
+/// Adds `Checklist:` and `Checklist-Skipped:` trailers for the saved progress to the commit
+/// message in `message_path`. A checklist without saved progress adds none and doesn't fail
+pub fn run(
+    store: &Store,
+    checklist_path: &String,
+    name: Option<&str>,
+    message_path: &Path,
+) -> Result<ExitCode> {
+    let mut checklist = Checklist::load(checklist_path, name)?;
+    let head = git(&["rev-parse", "HEAD"]);
+    if !store.load_progress(&mut checklist, checklist_path, head.as_deref())? {
+        warn!(
+            "No progress saved for {:?}, no trailers added",
+            checklist.name
+        );
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let trailers: Vec<String> =
+        std::iter::once(format!("{CHECKLIST_TRAILER}: {}", checklist.to_trailer()))
+            .chain(
+                checklist
+                    .skipped_items()
+                    .into_iter()
+                    .map(|text| format!("{SKIPPED_TRAILER}: {text}")),
+            )
+            .collect();
+    let read_message = || {
+        fs::read_to_string(message_path).map_err(|error| KneeboardError::io(message_path, error))
+    };
+    let has_subject = has_subject(&read_message()?);
+    let message_file = message_path.to_string_lossy();
+    // Edited in place so the rest of the message, like the comments of a template, is kept
+    let mut args = vec![
+        "interpret-trailers",
+        "--in-place",
+        "--if-exists",
+        "addIfDifferent",
+    ];
+    for trailer in &trailers {
+        args.extend(["--trailer", trailer.as_str()]);
+    }
+    args.push(&message_file);
+    if !git_succeeds(&args) {
+        return Err(KneeboardError::io(
+            message_path,
+            io::Error::other("git interpret-trailers failed"),
+        ));
+    }
+    // Git puts the trailers right below the empty subject line of a template, where the subject
+    // typed above them would pull them into its paragraph and stop them being trailers
+    if !has_subject {
+        fs::write(message_path, format!("\n{}", read_message()?))
+            .map_err(|error| KneeboardError::io(message_path, error))?;
+    }
+    info!(
+        "Added {} trailers to {}",
+        trailers.len(),
+        message_path.display()
+    );
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Whether the message has anything but blank lines and comments, like the template
+/// `prepare-commit-msg` is handed before the subject is typed
+fn has_subject(message: &str) -> bool {
+    message
+        .lines()
+        .any(|line| !line.trim().is_empty() && !line.starts_with('#'))
+}
@@ -0,0 +1,75 @@
+use crate::checklist::{CHECKLIST_TRAILER, Checklist, ChecklistTrailer, SKIPPED_TRAILER};
+use crate::error::{KneeboardError, Result};
+use crate::helpers::git::git;
+use log::warn;
+use std::process::ExitCode;
+
+/// The values of the `key` trailers of `commit`
+fn trailer_values(commit: &str, key: &str) -> Vec<String> {
+    git(&[
+        "log",
+        "-1",
+        &format!("--format=%(trailers:key={key},valueonly)"),
+        commit,
+    ])
+    .map(|values| values.lines().map(str::to_string).collect())
+    .unwrap_or_default()
+}
+
+/// Checks the commit has a `Checklist:` trailer with every required item resolved, for the
+/// given checklist or for any checklist when none is given
+pub fn run(rev: &str, checklist_path: Option<&String>, name: Option<&str>) -> Result<ExitCode> {
+    let commit = git(&[
+        "rev-parse",
+        "--verify",
+        "--quiet",
+        &format!("{rev}^{{commit}}"),
+    ])
+    .ok_or_else(|| KneeboardError::config(format!("{rev:?} is not a commit")))?;
+    let expected = match checklist_path {
+        Some(checklist_path) => Some(Checklist::load(checklist_path, name)?.name),
+        None => None,
+    };
+
+    let mut verified = true;
+    let mut found = false;
+    for value in trailer_values(&commit, CHECKLIST_TRAILER) {
+        let trailer: ChecklistTrailer = match value.parse() {
+            Ok(trailer) => trailer,
+            Err(error) => {
+                warn!("{rev}: {CHECKLIST_TRAILER} trailer {error}");
+                verified = false;
+                continue;
+            }
+        };
+        if expected.as_ref().is_some_and(|name| *name != trailer.name) {
+            continue;
+        }
+        found = true;
+        if trailer.is_complete() {
+            println!("{rev}: {trailer} complete");
+        } else {
+            println!(
+                "{rev}: {trailer}, {} required items unresolved",
+                trailer.required - trailer.resolved
+            );
+            verified = false;
+        }
+    }
+    if !found {
+        match &expected {
+            Some(name) => println!("{rev}: no {CHECKLIST_TRAILER} trailer for {name:?}"),
+            None => println!("{rev}: no {CHECKLIST_TRAILER} trailer"),
+        }
+        verified = false;
+    }
+    for text in trailer_values(&commit, SKIPPED_TRAILER) {
+        println!("{rev}: skipped {text:?}");
+    }
+
+    Ok(if verified {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
@@ -0,0 +1,187 @@
+use crate::error::{KneeboardError, Result};
+use crate::helpers::git::git;
+use crate::save::{Scope, StateLocation};
+use log::debug;
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+pub const CONFIG_FILE_NAME: &str = "kneeboard.toml";
+
+/// The profile used when none is picked and the config file doesn't name one
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Settings read from `kneeboard.toml`, command line flags and environment variables win over them
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    /// Where progress is kept: `checklist`, `xdg` or `git`
+    pub state: Option<String>,
+    /// Directory progress is kept in, relative to the config file
+    pub state_dir: Option<PathBuf>,
+    /// What progress is kept apart by: `shared`, `branch` or `worktree`
+    pub scope: Option<String>,
+    /// Profile used when none is picked, `default` unless given
+    pub default_profile: Option<String>,
+    pub profiles: BTreeMap<String, Profile>,
+    /// The file the config was loaded from
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+}
+
+/// A named set of options for running a checklist, picked with `--profile`
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Profile {
+    /// Path to the checklist, relative to the config file
+    pub checklist: Option<PathBuf>,
+    pub name: Option<String>,
+    pub save: Option<bool>,
+    pub write_back: Option<bool>,
+    /// Report as given to `--report`
+    pub report: Option<String>,
+    /// UI mode as given to `--ui`
+    pub ui: Option<String>,
+    pub headless: Option<bool>,
+    pub no_run: Option<bool>,
+    pub run_timeout: Option<u64>,
+    pub state: Option<String>,
+    pub state_dir: Option<PathBuf>,
+    pub scope: Option<String>,
+}
+
+impl Config {
+    pub fn from_toml(input: &str) -> Result<Config> {
+        toml::from_str::<Config>(input).map_err(|error| KneeboardError::toml(input, error))
+    }
+
+    /// Loads the first `kneeboard.toml` found at the root of the current repository, or the
+    /// current directory outside of one, then in `$XDG_CONFIG_HOME/kneeboard`.
+    /// No config file gives the defaults
+    pub fn discover() -> Result<Config> {
+        let root = git(&["rev-parse", "--show-toplevel"])
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from("."));
+        let config_home = env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")));
+        let candidates = [Some(root), config_home.map(|dir| dir.join("kneeboard"))];
+        for path in candidates.into_iter().flatten() {
+            let path = path.join(CONFIG_FILE_NAME);
+            if path.exists() {
+                return Config::load(&path);
+            }
+            debug!("No config file at {}", path.display());
+        }
+        Ok(Config::default())
+    }
+
+    pub fn load(path: &Path) -> Result<Config> {
+        let file_contents = fs::read_to_string(path)
+            .map_err(|error| KneeboardError::config_file(path, KneeboardError::io(path, error)))?;
+        let mut config = Config::from_toml(&file_contents)
+            .map_err(|error| KneeboardError::config_file(path, error))?;
+        if let Some(config_dir) = path.parent() {
+            config.resolve_paths(config_dir);
+        }
+        config.path = Some(path.to_path_buf());
+        debug!("Loaded config from {}", path.display());
+        Ok(config)
+    }
+
+    /// Makes the paths in the config relative to the directory of the config file
+    fn resolve_paths(&mut self, config_dir: &Path) {
+        let resolve = |path: &mut Option<PathBuf>| {
+            if let Some(path) = path {
+                *path = config_dir.join(&*path);
+            }
+        };
+        resolve(&mut self.state_dir);
+        for profile in self.profiles.values_mut() {
+            resolve(&mut profile.checklist);
+            resolve(&mut profile.state_dir);
+        }
+    }
+
+    /// The profile called `name`, or the default profile when no name is given.
+    /// Only a profile picked by name has to exist
+    pub fn profile(&self, name: Option<&str>) -> Result<Option<&Profile>> {
+        let Some(name) = name.or(self.default_profile.as_deref()) else {
+            return Ok(self.profiles.get(DEFAULT_PROFILE));
+        };
+        match self.profiles.get(name) {
+            Some(profile) => Ok(Some(profile)),
+            None => Err(KneeboardError::config(match &self.path {
+                Some(path) => format!("no profile {name:?} in {}", path.display()),
+                None => format!("no profile {name:?}, no {CONFIG_FILE_NAME} was found"),
+            })),
+        }
+    }
+
+    /// Picks the state location from, in order, the flags, `KNEEBOARD_STATE_DIR`,
+    /// `KNEEBOARD_STATE`, the profile and the rest of the config file
+    pub fn state_location(
+        &self,
+        profile: Option<&Profile>,
+        state: Option<&StateLocation>,
+        state_dir: Option<&Path>,
+    ) -> Result<StateLocation> {
+        if let Some(state_dir) = state_dir {
+            return Ok(StateLocation::Dir(state_dir.to_path_buf()));
+        }
+        if let Some(state) = state {
+            return Ok(state.clone());
+        }
+        if let Some(state_dir) = env::var_os("KNEEBOARD_STATE_DIR").filter(|dir| !dir.is_empty()) {
+            return Ok(StateLocation::Dir(PathBuf::from(state_dir)));
+        }
+        if let Some(state) = env::var("KNEEBOARD_STATE")
+            .ok()
+            .filter(|state| !state.is_empty())
+        {
+            return state.parse().map_err(|error: String| {
+                KneeboardError::config(format!("KNEEBOARD_STATE: {error}"))
+            });
+        }
+        let configured = [profile.map(|profile| (&profile.state_dir, &profile.state))]
+            .into_iter()
+            .flatten()
+            .chain([(&self.state_dir, &self.state)]);
+        for (state_dir, state) in configured {
+            if let Some(state_dir) = state_dir {
+                return Ok(StateLocation::Dir(state_dir.clone()));
+            }
+            if let Some(state) = state {
+                return state.parse().map_err(KneeboardError::config);
+            }
+        }
+        Ok(StateLocation::default())
+    }
+
+    /// Picks the scope from, in order, the flag, `KNEEBOARD_SCOPE`, the profile and the rest
+    /// of the config file
+    pub fn scope(&self, profile: Option<&Profile>, scope: Option<Scope>) -> Result<Scope> {
+        if let Some(scope) = scope {
+            return Ok(scope);
+        }
+        if let Some(scope) = env::var("KNEEBOARD_SCOPE")
+            .ok()
+            .filter(|scope| !scope.is_empty())
+        {
+            return scope.parse().map_err(|error: String| {
+                KneeboardError::config(format!("KNEEBOARD_SCOPE: {error}"))
+            });
+        }
+        match profile
+            .and_then(|profile| profile.scope.as_ref())
+            .or(self.scope.as_ref())
+        {
+            Some(scope) => scope.parse().map_err(KneeboardError::config),
+            None => Ok(Scope::default()),
+        }
+    }
+}
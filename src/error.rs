@@ -0,0 +1,189 @@
+use std::{error, fmt, io, path::PathBuf};
+
+pub type Result<T> = std::result::Result<T, KneeboardError>;
+
+/// The kind of document an error relates to
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Markdown,
+    Toml,
+    Json,
+}
+
+/// A 1-based line and column in a document
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug)]
+pub enum KneeboardError {
+    /// A document that couldn't be parsed
+    Parse {
+        format: Format,
+        message: String,
+        location: Option<Location>,
+        source: Option<Box<dyn error::Error + Send + Sync>>,
+    },
+    /// A document that couldn't be generated
+    Serialize {
+        format: Format,
+        source: Box<dyn error::Error + Send + Sync>,
+    },
+    /// A file that couldn't be read or written
+    Io { path: PathBuf, source: io::Error },
+    /// A save file that couldn't be loaded or saved
+    SaveFile {
+        path: PathBuf,
+        source: Box<KneeboardError>,
+    },
+    /// A config file that couldn't be loaded
+    ConfigFile {
+        path: PathBuf,
+        source: Box<KneeboardError>,
+    },
+    /// Settings that can't be used together or don't make sense where kneeboard runs
+    Config { message: String },
+    /// A checklist that parsed but doesn't follow the spec or the request made of it
+    Validation {
+        message: String,
+        location: Option<Location>,
+    },
+}
+
+impl Location {
+    /// Converts a byte offset into `input` to a line and column
+    pub fn from_offset(input: &str, offset: usize) -> Location {
+        let before = &input[..offset.min(input.len())];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        Location {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+}
+
+impl KneeboardError {
+    pub fn io(path: impl Into<PathBuf>, source: io::Error) -> KneeboardError {
+        KneeboardError::Io {
+            path: path.into(),
+            source,
+        }
+    }
+
+    pub fn save_file(path: impl Into<PathBuf>, source: KneeboardError) -> KneeboardError {
+        KneeboardError::SaveFile {
+            path: path.into(),
+            source: Box::new(source),
+        }
+    }
+
+    pub fn config_file(path: impl Into<PathBuf>, source: KneeboardError) -> KneeboardError {
+        KneeboardError::ConfigFile {
+            path: path.into(),
+            source: Box::new(source),
+        }
+    }
+
+    pub fn config(message: impl Into<String>) -> KneeboardError {
+        KneeboardError::Config {
+            message: message.into(),
+        }
+    }
+
+    pub fn validation(message: impl Into<String>) -> KneeboardError {
+        KneeboardError::Validation {
+            message: message.into(),
+            location: None,
+        }
+    }
+
+    /// A checklist problem at the byte `offset` into the Markdown `input`
+    pub fn validation_at(message: impl Into<String>, input: &str, offset: usize) -> KneeboardError {
+        KneeboardError::Validation {
+            message: message.into(),
+            location: Some(Location::from_offset(input, offset)),
+        }
+    }
+
+    /// Wraps a TOML error, keeping where in `input` it happened
+    pub fn toml(input: &str, source: toml::de::Error) -> KneeboardError {
+        KneeboardError::Parse {
+            format: Format::Toml,
+            message: source.message().to_string(),
+            location: source
+                .span()
+                .map(|span| Location::from_offset(input, span.start)),
+            source: Some(Box::new(source)),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::Markdown => write!(f, "Markdown"),
+            Format::Toml => write!(f, "TOML"),
+            Format::Json => write!(f, "JSON"),
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+impl fmt::Display for KneeboardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KneeboardError::Parse {
+                format,
+                message,
+                location: Some(location),
+                ..
+            } => write!(f, "failed to parse {format} at {location}: {message}"),
+            KneeboardError::Parse {
+                format, message, ..
+            } => write!(f, "failed to parse {format}: {message}"),
+            KneeboardError::Serialize { format, source } => {
+                write!(f, "failed to generate {format}: {source}")
+            }
+            KneeboardError::Io { path, source } => {
+                write!(f, "failed to access {}: {source}", path.display())
+            }
+            KneeboardError::SaveFile { path, source } => {
+                write!(f, "save file {}: {source}", path.display())
+            }
+            KneeboardError::ConfigFile { path, source } => {
+                write!(f, "config file {}: {source}", path.display())
+            }
+            KneeboardError::Config { message } => write!(f, "invalid configuration: {message}"),
+            KneeboardError::Validation {
+                message,
+                location: Some(location),
+            } => write!(f, "invalid checklist at {location}: {message}"),
+            KneeboardError::Validation { message, .. } => {
+                write!(f, "invalid checklist: {message}")
+            }
+        }
+    }
+}
+
+impl error::Error for KneeboardError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            KneeboardError::Parse {
+                source: Some(source),
+                ..
+            } => Some(source.as_ref()),
+            KneeboardError::Serialize { source, .. } => Some(source.as_ref()),
+            KneeboardError::Io { source, .. } => Some(source),
+            KneeboardError::SaveFile { source, .. } => Some(source.as_ref()),
+            KneeboardError::ConfigFile { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use log::debug;
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Runs git with `args` in the current directory, returning its trimmed stdout when it succeeds
+pub fn git(args: &[&str]) -> Option<String> {
+    git_in(Path::new("."), args)
+}
+
+/// Runs git with `args` in `dir`, returning its trimmed stdout when it succeeds
+pub fn git_in(dir: &Path, args: &[&str]) -> Option<String> {
+    git_output(dir, args)
+        .map(|stdout| stdout.trim().to_string())
+        .filter(|stdout| !stdout.is_empty())
+}
+
+/// Runs git with `args` in the current directory, returning whether it succeeded
+pub fn git_succeeds(args: &[&str]) -> bool {
+    git_output(Path::new("."), args).is_some()
+}
+
+/// Runs git with `args` in `dir`, returning its stdout as is when it succeeds
+fn git_output(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = match Command::new("git").current_dir(dir).args(args).output() {
+        Ok(output) => output,
+        Err(error) => {
+            debug!("[git] Failed to run git {args:?}: {error}");
+            return None;
+        }
+    };
+    if !output.status.success() {
+        debug!(
+            "[git] git {args:?} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Runs git with `args` in the current directory feeding `input` to its stdin, returning its
+/// trimmed stdout, even when empty, when it succeeds
+pub fn git_with_input(args: &[&str], input: &str) -> Option<String> {
+    let output = Command::new("git")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .and_then(|mut child| {
+            child
+                .stdin
+                .take()
+                .ok_or_else(|| io::Error::other("no stdin"))?
+                .write_all(input.as_bytes())?;
+            child.wait_with_output()
+        });
+    let output = match output {
+        Ok(output) => output,
+        Err(error) => {
+            debug!("[git] Failed to run git {args:?}: {error}");
+            return None;
+        }
+    };
+    if !output.status.success() {
+        debug!(
+            "[git] git {args:?} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The path relative to the root of the current repository, or its canonical path outside of it
+pub fn repo_relative_path(path: &str) -> io::Result<String> {
+    let canonical_path = fs::canonicalize(path)?;
+    let root = git(&["rev-parse", "--show-toplevel"]).and_then(|root| fs::canonicalize(root).ok());
+    let path = match root.and_then(|root| canonical_path.strip_prefix(root).ok()) {
+        Some(relative_path) => relative_path,
+        None => &canonical_path,
+    };
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// The git `user.name`, falling back to `$USER` or `$USERNAME`
+pub fn current_user() -> String {
+    git(&["config", "user.name"])
+        .or_else(|| env::var("USER").ok())
+        .or_else(|| env::var("USERNAME").ok())
+        .filter(|user| !user.is_empty())
+        .unwrap_or_else(|| String::from("unknown"))
+}
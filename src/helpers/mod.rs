@@ -1,3 +1,7 @@
+pub mod git;
+
 pub mod logger;
 
+pub mod prompt;
+
 pub mod ui;
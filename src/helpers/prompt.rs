@@ -0,0 +1,129 @@
+use crate::checklist::{Checklist, ChecklistMode};
+use std::io::{self, BufRead, Write};
+
+/// What was answered for an item
+enum Answer {
+    Yes,
+    No,
+    Skip,
+    /// The input ran out before the item was answered
+    Unanswered,
+}
+
+/// Asks about every unresolved item in turn over plain lines of text, for terminals
+/// the TUI can't draw on. Answers are `y`, `n`, `skip` or `note <text>`, running out
+/// of input leaves the remaining items as they were. Items whose command failed can't be
+/// answered, skipping one of them or any other item of a sequential checklist is recorded
+/// on the item
+pub fn prompt(
+    checklist: Checklist,
+    user: &str,
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> io::Result<Checklist> {
+    let mut final_checklist = checklist.clone();
+    let items = checklist.walk();
+    let total = items.len();
+    let sequential = checklist.mode == ChecklistMode::Sequential;
+    let mut answers: Vec<Option<(Answer, Option<String>)>> = Vec::new();
+
+    writeln!(output, "{}", checklist.name)?;
+    for (index, (depth, item)) in items.iter().enumerate() {
+        let mut label = format!(
+            "[{}/{total}] {}{}",
+            index + 1,
+            "  ".repeat(*depth),
+            item.text
+        );
+        if let Some(response) = &item.response {
+            label.push_str(&format!(" :: {response}"));
+        }
+        if item.optional {
+            label.push_str(" (optional)");
+        }
+
+        if item.resolved {
+            answers.push(None);
+            continue;
+        }
+        if let Some(command) = item.run.as_ref().filter(|_| item.run_failed) {
+            // Items whose command failed are resolved by it passing, not by hand
+            if !sequential {
+                writeln!(output, "{label} - failed `{command}`")?;
+                answers.push(None);
+                continue;
+            }
+            // Later items wait on it unless it is skipped on purpose
+            let answer = loop {
+                write!(output, "{label} - failed `{command}`, skip it? [skip/n] ")?;
+                output.flush()?;
+                let mut line = String::new();
+                if input.read_line(&mut line)? == 0 {
+                    writeln!(output)?;
+                    break Answer::Unanswered;
+                }
+                match line.trim() {
+                    "s" | "skip" => break Answer::Skip,
+                    "n" | "no" => break Answer::No,
+                    _ => writeln!(output, "Answer skip or n")?,
+                }
+            };
+            let stop = !matches!(answer, Answer::Skip);
+            answers.push(Some((answer, item.note.clone())));
+            if stop {
+                writeln!(output, "Stopping, {:?} has to pass first", item.text)?;
+                break;
+            }
+            continue;
+        }
+
+        let mut note = item.note.clone();
+        let answer = loop {
+            write!(output, "{label}? [y/n/skip/note] ")?;
+            output.flush()?;
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                writeln!(output)?;
+                break None;
+            }
+            match line.trim() {
+                "y" | "yes" => break Some(Answer::Yes),
+                "n" | "no" => break Some(Answer::No),
+                "s" | "skip" => break Some(Answer::Skip),
+                answer => match answer.strip_prefix("note") {
+                    Some(text) if !text.trim().is_empty() => {
+                        note = Some(text.trim().to_string());
+                        writeln!(output, "Noted")?;
+                    }
+                    _ => writeln!(output, "Answer y, n, skip or note <text>")?,
+                },
+            }
+        };
+
+        let Some(answer) = answer else {
+            // No more input, the remaining items are left as they were
+            answers.push(Some((Answer::Unanswered, note)));
+            break;
+        };
+        let stop = sequential && matches!(answer, Answer::No);
+        answers.push(Some((answer, note)));
+        if stop {
+            writeln!(output, "Stopping, {:?} has to be done first", item.text)?;
+            break;
+        }
+    }
+
+    let mut index = 0;
+    final_checklist.walk_mut(|_, item| {
+        if let Some(Some((answer, note))) = answers.get(index) {
+            match answer {
+                Answer::Yes => item.set_resolved(true, user),
+                Answer::Skip if sequential => item.skip(user),
+                _ => (),
+            }
+            item.note.clone_from(note);
+        }
+        index += 1;
+    });
+    Ok(final_checklist)
+}
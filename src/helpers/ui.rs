@@ -1,14 +1,25 @@
-use crate::checklist::Checklist;
-use crate::checklist::ChecklistItem;
+use crate::checklist::{Checklist, ChecklistItem, ChecklistMode};
+use cursive::Cursive;
 use cursive::theme::{BorderStyle, Palette};
 use cursive::traits::*;
-use cursive::views::{NamedView, Panel, ViewRef};
+use cursive::views::{
+    Dialog, DummyView, LinearLayout, NamedView, Panel, TextArea, TextView, ViewRef,
+};
 use cursive::{
     view::Resizable,
     views::{Checkbox, ListView},
 };
 
-pub fn draw(checklist: Checklist) -> Checklist {
+/// State of the run, kept as the user data of the TUI.
+/// Items are in the order of `Checklist::walk` without their children
+struct RunState {
+    items: Vec<ChecklistItem>,
+    /// Items skipped out of order in a sequential checklist, in this run or an earlier one
+    skipped: Vec<bool>,
+    user: String,
+}
+
+pub fn draw(checklist: Checklist, user: &str) -> Checklist {
     // Creates the cursive root - required for every application.
     let mut siv = cursive::default();
 
@@ -33,52 +44,205 @@ pub fn draw(checklist: Checklist) -> Checklist {
     siv.add_global_callback('w', |s| s.quit());
 
     siv.add_global_callback('~', |s| s.toggle_debug_console());
+    siv.add_global_callback('n', edit_note);
 
+    let sequential = checklist.mode == ChecklistMode::Sequential;
     let mut checklist_view = ListView::new();
 
-    for checklist_item in &checklist.items {
-        if checklist_item.resolved {
-            checklist_view.add_child(
-                &checklist_item.text,
-                NamedView::new(&checklist_item.text, Checkbox::new().checked()),
-            )
-        } else {
-            checklist_view.add_child(
-                &checklist_item.text,
-                NamedView::new(&checklist_item.text, Checkbox::new()),
-            )
+    let items = checklist.walk();
+    // Responses line up in a column after the longest challenge
+    let challenge_width = items
+        .iter()
+        .filter(|(_, item)| item.response.is_some())
+        .map(|(depth, item)| item_label(*depth, item).chars().count())
+        .max()
+        .unwrap_or_default();
+
+    for (index, (depth, checklist_item)) in items.into_iter().enumerate() {
+        let mut label = item_label(depth, checklist_item);
+        if let Some(response) = &checklist_item.response {
+            label = format!("{label:<challenge_width$}  {response}");
         }
+        let mut checkbox = Checkbox::new().with_checked(checklist_item.resolved);
+        // Items whose command failed are resolved by it passing, not by hand
+        checkbox.set_enabled(!checklist_item.run_failed);
+        checkbox.set_on_change(move |s, checked| {
+            let resolution = s.with_user_data(|state: &mut RunState| {
+                let user = state.user.clone();
+                let item = &mut state.items[index];
+                item.set_resolved(checked, &user);
+                item.resolution()
+            });
+            s.call_on_name(&resolution_view_name(index), |view: &mut TextView| {
+                view.set_content(resolution.flatten().unwrap_or_default());
+            });
+            if sequential {
+                update_sequence(s);
+            }
+        });
+        let resolution = TextView::new(checklist_item.resolution().unwrap_or_default())
+            .with_name(resolution_view_name(index));
+        checklist_view.add_child(
+            &label,
+            LinearLayout::horizontal()
+                .child(NamedView::new(item_view_name(index), checkbox))
+                .child(DummyView)
+                .child(resolution),
+        )
     }
 
-    let mut main_panel = Panel::new(checklist_view.scrollable());
+    let mut main_panel = Panel::new(checklist_view.with_name(LIST_VIEW_NAME).scrollable());
 
     main_panel.set_title(&checklist.name);
     // Creates a dialog with a single "Quit" button
     siv.add_fullscreen_layer(main_panel.full_width());
 
+    siv.set_user_data(RunState {
+        items: checklist
+            .walk()
+            .into_iter()
+            .map(|(_, item)| ChecklistItem {
+                children: Vec::new(),
+                ..item.clone()
+            })
+            .collect(),
+        skipped: checklist
+            .walk()
+            .iter()
+            .map(|(_, item)| item.skipped_at.is_some() && !item.resolved)
+            .collect(),
+        user: user.to_string(),
+    });
+
+    if sequential {
+        siv.add_global_callback('s', confirm_skip);
+        update_sequence(&mut siv);
+    }
+
     // Starts the event loop.
     siv.run();
 
-    let mut final_checklist = Checklist {
-        items: Vec::<ChecklistItem>::new(),
-        name: checklist.name.clone(),
+    let state = siv.take_user_data::<RunState>().unwrap();
+
+    let mut final_checklist = checklist.clone();
+    let mut index = 0;
+    final_checklist.walk_mut(|_, checklist_item: &mut ChecklistItem| {
+        let checkboxview: ViewRef<Checkbox> = siv.find_name(&item_view_name(index)).unwrap();
+        let item = &state.items[index];
+        checklist_item.resolved = checkboxview.is_checked();
+        checklist_item.resolved_at = item.resolved_at;
+        checklist_item.resolved_by.clone_from(&item.resolved_by);
+        checklist_item.skipped_at = item.skipped_at;
+        checklist_item.skipped_by.clone_from(&item.skipped_by);
+        checklist_item.note.clone_from(&item.note);
+        index += 1;
+    });
+    final_checklist
+}
+
+const LIST_VIEW_NAME: &str = "checklist-items";
+const NOTE_VIEW_NAME: &str = "checklist-note";
+
+fn item_label(depth: usize, checklist_item: &ChecklistItem) -> String {
+    format!("{}{}", "    ".repeat(depth), checklist_item.text)
+}
+
+fn item_view_name(index: usize) -> String {
+    format!("checklist-item-{index}")
+}
+
+fn resolution_view_name(index: usize) -> String {
+    format!("checklist-item-resolution-{index}")
+}
+
+/// The first item that is neither ticked nor skipped
+fn next_item(s: &mut Cursive) -> Option<usize> {
+    let skipped = s.with_user_data(|state: &mut RunState| state.skipped.clone())?;
+    (0..skipped.len()).find(|&index| {
+        !skipped[index]
+            && !s
+                .call_on_name(&item_view_name(index), |checkbox: &mut Checkbox| {
+                    checkbox.is_checked()
+                })
+                .unwrap_or(true)
+    })
+}
+
+/// Only the next item can be ticked, earlier items are locked once confirmed
+fn update_sequence(s: &mut Cursive) {
+    let next = next_item(s);
+    // Items whose command failed can't be ticked by hand
+    let Some(manual) = s.with_user_data(|state: &mut RunState| {
+        state
+            .items
+            .iter()
+            .map(|item| !item.run_failed)
+            .collect::<Vec<bool>>()
+    }) else {
+        return;
     };
+    for (index, manual) in manual.into_iter().enumerate() {
+        s.call_on_name(&item_view_name(index), |checkbox: &mut Checkbox| {
+            checkbox.set_enabled(manual && next == Some(index));
+        });
+    }
+}
 
-    for checklist_item in &checklist.items {
-        let checkboxview: ViewRef<Checkbox> = siv.find_name(&checklist_item.text).unwrap();
-        if checkboxview.is_checked() {
-            final_checklist.items.push(ChecklistItem {
-                text: checklist_item.text.clone(),
-                optional: checklist_item.optional,
-                resolved: true,
-            })
-        } else {
-            final_checklist.items.push(ChecklistItem {
-                text: checklist_item.text.clone(),
-                optional: checklist_item.optional,
-                resolved: false,
+/// Asks before skipping the next item, the skip is recorded on the item with who skipped it
+fn confirm_skip(s: &mut Cursive) {
+    let Some(next) = next_item(s) else {
+        return;
+    };
+    let Some(text) = s.with_user_data(|state: &mut RunState| state.items[next].text.clone()) else {
+        return;
+    };
+    s.add_layer(
+        Dialog::text(format!(
+            "Skip {text:?} out of order? The skip is recorded with your name."
+        ))
+        .title("Skip item")
+        .button("Skip", move |s| {
+            s.with_user_data(|state: &mut RunState| {
+                let user = state.user.clone();
+                state.items[next].skip(&user);
+                state.skipped[next] = true;
+            });
+            s.pop_layer();
+            update_sequence(s);
+        })
+        .dismiss_button("Cancel"),
+    );
+}
+
+/// Opens an editor for the note of the focused item, an empty note removes it
+fn edit_note(s: &mut Cursive) {
+    let Some(index) = s.call_on_name(LIST_VIEW_NAME, |list: &mut ListView| list.focus()) else {
+        return;
+    };
+    let Some((text, note)) = s.with_user_data(|state: &mut RunState| {
+        let item = &state.items[index];
+        (item.text.clone(), item.note.clone())
+    }) else {
+        return;
+    };
+    let editor = TextArea::new()
+        .content(note.unwrap_or_default())
+        .with_name(NOTE_VIEW_NAME)
+        .min_size((50, 5));
+    s.add_layer(
+        Dialog::around(editor)
+            .title(format!("Note for {text}"))
+            .button("Save", move |s| {
+                let note = s
+                    .call_on_name(NOTE_VIEW_NAME, |editor: &mut TextArea| {
+                        editor.get_content().trim().to_string()
+                    })
+                    .unwrap_or_default();
+                s.with_user_data(|state: &mut RunState| {
+                    state.items[index].note = Some(note).filter(|note| !note.is_empty());
+                });
+                s.pop_layer();
             })
-        }
-    }
-    final_checklist
+            .dismiss_button("Cancel"),
+    );
 }
@@ -1,35 +1,365 @@
 mod checklist;
+mod commands;
+mod config;
+mod error;
 mod helpers;
+mod save;
 
-use crate::checklist::Checklist;
+use crate::checklist::{Checklist, ReportTarget, Reset, RunRecord};
+use crate::commands::hooks::Hook;
+use crate::config::{Config, Profile};
+use crate::error::{KneeboardError, Result};
+use crate::helpers::git::{current_user, git};
 use crate::helpers::logger::setup_logger;
+use crate::helpers::prompt::prompt;
 use crate::helpers::ui::draw;
+use crate::save::{Scope, StateLocation, Store};
 use log::{LevelFilter, debug, error, info, warn};
-use std::{error, fs, path::Path, process::ExitCode};
-use xxhash_rust::xxh3::xxh3_64;
+use std::{
+    env, fs,
+    io::{self, IsTerminal},
+    path::PathBuf,
+    process::ExitCode,
+    time::Duration,
+};
 
-use clap::Parser as clapParser;
+use clap::{Parser as clapParser, Subcommand, ValueEnum};
 
-type Result<T> = std::result::Result<T, Box<dyn error::Error>>;
+/// Seconds item commands may run when no timeout is given
+const DEFAULT_RUN_TIMEOUT: u64 = 300;
 
 #[derive(clapParser, Debug)]
 #[clap(author, version, about, long_about = None)]
 struct Args {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
     /// Path to the checklist
-    #[clap(short, long, value_parser, required(true))]
-    checklist_path: String,
+    #[clap(short, long, value_parser)]
+    checklist_path: Option<String>,
 
-    /// Save and load progress of the checklist
+    /// Profile from kneeboard.toml to run, default unless given
+    #[clap(short, long, value_parser, value_name = "NAME", global = true)]
+    profile: Option<String>,
+
+    /// Name of the checklist to use from the file
     #[clap(short, long, value_parser)]
+    name: Option<String>,
+
+    /// Save and load progress of the checklist
+    #[clap(short, long, value_parser, overrides_with = "no_save")]
     save: bool,
 
+    /// Don't save progress, even when the profile does
+    #[clap(long, value_parser, overrides_with = "save")]
+    no_save: bool,
+
+    /// Write progress back into the checklist file
+    #[clap(long, value_parser, overrides_with = "no_write_back")]
+    write_back: bool,
+
+    /// Don't write progress back, even when the profile does
+    #[clap(long, value_parser, overrides_with = "write_back")]
+    no_write_back: bool,
+
+    /// Write a report of the run, to stdout unless a path is given
+    #[clap(long, value_parser, value_name = "FORMAT[=PATH]")]
+    report: Option<ReportTarget>,
+
     /// Turn debugging information on
-    #[clap(short, long, action = clap::ArgAction::Count)]
+    #[clap(short, long, action = clap::ArgAction::Count, global = true)]
     verbose: u8,
 
     /// Headless mode
-    #[clap(long, value_parser)]
+    #[clap(long, value_parser, overrides_with = "no_headless")]
     headless: bool,
+
+    /// Ask about items, even when the profile is headless
+    #[clap(long, value_parser, overrides_with = "headless")]
+    no_headless: bool,
+
+    /// Ask with auto, tui or prompt [default: auto]
+    #[clap(long, value_enum, value_name = "MODE", hide_possible_values = true)]
+    ui: Option<UiMode>,
+
+    /// Skip running item commands
+    #[clap(long, value_parser, overrides_with = "run")]
+    no_run: bool,
+
+    /// Run item commands, even when the profile skips them
+    #[clap(long, value_parser, overrides_with = "no_run")]
+    run: bool,
+
+    /// Seconds before an item command times out [default: 300]
+    #[clap(long, value_parser, value_name = "SECONDS")]
+    run_timeout: Option<u64>,
+
+    /// Keep progress in checklist, xdg, git, notes or notes-branch
+    #[clap(long, value_parser, value_name = "LOCATION", global = true)]
+    state: Option<StateLocation>,
+
+    /// Keep progress in this directory
+    #[clap(
+        long,
+        value_parser,
+        value_name = "DIR",
+        global = true,
+        conflicts_with = "state"
+    )]
+    state_dir: Option<PathBuf>,
+
+    /// Keep progress shared, by branch or by worktree
+    #[clap(long, value_parser, value_name = "SCOPE", global = true)]
+    scope: Option<Scope>,
+
+    /// Expire saved progress daily or on-new-commit instead
+    #[clap(long, value_parser, value_name = "RULE", global = true)]
+    reset_rule: Option<Reset>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum UiMode {
+    // Prompt without a capable terminal, otherwise the TUI
+    Auto,
+    Tui,
+    Prompt,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Check checklist files against SPEC.md
+    Lint {
+        /// Paths to the checklists to check
+        #[clap(value_parser, required(true))]
+        paths: Vec<String>,
+    },
+    /// List the recorded runs of a checklist or show one of them
+    History {
+        #[clap(flatten)]
+        checklist: ChecklistArgs,
+
+        /// Number of the run to show
+        #[clap(value_parser)]
+        run: Option<usize>,
+    },
+    /// Print the saved progress of a checklist and its outstanding items
+    Status {
+        #[clap(flatten)]
+        checklist: ChecklistArgs,
+    },
+    /// Clear the saved progress of all or the given items
+    Reset {
+        #[clap(flatten)]
+        checklist: ChecklistArgs,
+
+        /// Items to reset by text, id, number, glob or prefix, all items when none are given
+        #[clap(value_parser)]
+        items: Vec<String>,
+    },
+    /// Resolve items in the saved progress without the TUI
+    Check {
+        #[clap(flatten)]
+        items: ItemArgs,
+
+        /// Skip the open items before them in a sequential checklist, recording who skipped them
+        #[clap(long, value_parser)]
+        skip: bool,
+    },
+    /// Unresolve items in the saved progress without the TUI
+    Uncheck(ItemArgs),
+    /// Add checklist trailers for the saved progress to a commit message
+    Trailers {
+        #[clap(flatten)]
+        checklist: ChecklistArgs,
+
+        /// Commit message file, as given to the commit-msg hook
+        #[clap(value_parser)]
+        message_file: PathBuf,
+    },
+    /// Check a commit has checklist trailers with every required item resolved
+    VerifyCommit {
+        #[clap(flatten)]
+        checklist: ChecklistArgs,
+
+        /// Commit to check
+        #[clap(value_parser, default_value = "HEAD")]
+        rev: String,
+    },
+    /// Remove the saved progress of deleted branches and worktrees
+    Gc {
+        /// Path to the checklist
+        #[clap(short, long, value_parser)]
+        checklist_path: Option<String>,
+    },
+    /// Install, uninstall or list the git hooks running a checklist
+    Hooks {
+        #[clap(subcommand)]
+        action: HooksAction,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum HooksAction {
+    /// Install hooks running a checklist, a hook already there is run first
+    Install {
+        #[clap(flatten)]
+        checklist: ChecklistArgs,
+
+        /// Save progress from the pre-commit and pre-push hooks, starting over on every commit
+        #[clap(short, long, value_parser)]
+        save: bool,
+
+        /// Hooks to install, pre-commit when none are given
+        #[clap(value_enum)]
+        hooks: Vec<Hook>,
+    },
+    /// Remove the hooks kneeboard installed and put back the hooks they replaced
+    Uninstall {
+        /// Hooks to uninstall, all of them when none are given
+        #[clap(value_enum)]
+        hooks: Vec<Hook>,
+    },
+    /// List the hooks kneeboard can install and what runs them
+    List,
+}
+
+#[derive(clap::Args, Debug)]
+struct ItemArgs {
+    #[clap(flatten)]
+    checklist: ChecklistArgs,
+
+    /// Item to change by text, id, number, glob or prefix
+    #[clap(short, long, value_parser, required(true))]
+    item: Vec<String>,
+}
+
+/// The checklist a subcommand works on
+#[derive(clap::Args, Debug)]
+struct ChecklistArgs {
+    /// Path to the checklist
+    #[clap(short, long, value_parser)]
+    checklist_path: Option<String>,
+
+    /// Name of the checklist to use from the file
+    #[clap(short, long, value_parser)]
+    name: Option<String>,
+}
+
+impl Command {
+    /// The checklist a subcommand running it works on
+    fn checklist_mut(&mut self) -> Option<&mut ChecklistArgs> {
+        match self {
+            Command::History { checklist, .. }
+            | Command::Status { checklist }
+            | Command::Reset { checklist, .. }
+            | Command::Trailers { checklist, .. }
+            | Command::VerifyCommit { checklist, .. } => Some(checklist),
+            Command::Check { items: args, .. } | Command::Uncheck(args) => {
+                Some(&mut args.checklist)
+            }
+            Command::Lint { .. } | Command::Gc { .. } | Command::Hooks { .. } => None,
+        }
+    }
+}
+
+impl Args {
+    /// Hands the checklist given before the subcommand, like `kneeboard -c checklist.md status`,
+    /// to the subcommand when it wasn't given one of its own
+    fn inherit_checklist(&mut self) {
+        let (checklist_path, name) = match &mut self.command {
+            Some(Command::Gc { checklist_path }) => (checklist_path, None),
+            Some(Command::Hooks {
+                action: HooksAction::Install { checklist, .. },
+            }) => (&mut checklist.checklist_path, Some(&mut checklist.name)),
+            Some(command) => match command.checklist_mut() {
+                Some(checklist) => (&mut checklist.checklist_path, Some(&mut checklist.name)),
+                None => return,
+            },
+            None => return,
+        };
+        if checklist_path.is_none() {
+            checklist_path.clone_from(&self.checklist_path);
+        }
+        if let Some(name) = name
+            && name.is_none()
+        {
+            name.clone_from(&self.name);
+        }
+    }
+
+    /// Fills in the options not given on the command line from the profile
+    fn apply_profile(&mut self, profile: &Profile) -> Result<()> {
+        if let Some(checklist) = self.command.as_mut().and_then(Command::checklist_mut) {
+            checklist.apply_profile(profile);
+        }
+        if let Some(Command::Gc { checklist_path }) = &mut self.command
+            && checklist_path.is_none()
+        {
+            *checklist_path = profile_checklist_path(profile);
+        }
+        if self.checklist_path.is_none() {
+            self.checklist_path = profile_checklist_path(profile);
+        }
+        if self.name.is_none() {
+            self.name.clone_from(&profile.name);
+        }
+        // A flag either way wins over the profile
+        let merge = |flag: &mut bool, negated: bool, profile: Option<bool>| {
+            if !*flag && !negated {
+                *flag = profile.unwrap_or_default();
+            }
+        };
+        merge(&mut self.save, self.no_save, profile.save);
+        merge(&mut self.write_back, self.no_write_back, profile.write_back);
+        merge(&mut self.headless, self.no_headless, profile.headless);
+        merge(&mut self.no_run, self.run, profile.no_run);
+        if self.report.is_none()
+            && let Some(report) = &profile.report
+        {
+            self.report = Some(report.parse().map_err(KneeboardError::config)?);
+        }
+        if self.ui.is_none()
+            && let Some(ui) = &profile.ui
+        {
+            self.ui = Some(UiMode::from_str(ui, false).map_err(|_| {
+                KneeboardError::config(format!(
+                    "unknown ui mode {ui:?}, expected auto, tui or prompt"
+                ))
+            })?);
+        }
+        self.run_timeout = self.run_timeout.or(profile.run_timeout);
+        Ok(())
+    }
+}
+
+impl ChecklistArgs {
+    fn apply_profile(&mut self, profile: &Profile) {
+        if self.checklist_path.is_none() {
+            self.checklist_path = profile_checklist_path(profile);
+        }
+        if self.name.is_none() {
+            self.name.clone_from(&profile.name);
+        }
+    }
+
+    fn path(&self) -> Result<&String> {
+        checklist_path_given(&self.checklist_path)
+    }
+}
+
+fn profile_checklist_path(profile: &Profile) -> Option<String> {
+    profile
+        .checklist
+        .as_ref()
+        .map(|path| path.to_string_lossy().into_owned())
+}
+
+fn checklist_path_given(checklist_path: &Option<String>) -> Result<&String> {
+    checklist_path.as_ref().ok_or_else(|| {
+        KneeboardError::config(
+            "no checklist given, pass --checklist-path or set a checklist in a profile",
+        )
+    })
 }
 
 fn verbosity(level: u8) {
@@ -60,30 +390,146 @@ fn main() -> ExitCode {
 }
 
 fn main_sub() -> Result<ExitCode> {
-    let args = Args::parse();
+    let mut args = Args::parse();
     verbosity(args.verbose);
+
+    args.inherit_checklist();
+    let config = Config::discover()?;
+    let profile = config.profile(args.profile.as_deref())?;
+    if let Some(profile) = profile {
+        args.apply_profile(profile)?;
+    }
+    let store = Store::new(
+        config.state_location(profile, args.state.as_ref(), args.state_dir.as_deref())?,
+        config.scope(profile, args.scope)?,
+        args.reset_rule,
+    )?;
+    debug!(
+        "Keeping {} progress in the {} state location",
+        store.scope, store.location
+    );
+
+    match &args.command {
+        Some(Command::Lint { paths }) => Ok(commands::lint::run(paths)),
+        Some(Command::History { checklist, run }) => {
+            commands::history::run(&store, checklist.path()?, checklist.name.as_deref(), *run)
+        }
+        Some(Command::Status { checklist }) => {
+            commands::status::run(&store, checklist.path()?, checklist.name.as_deref())
+        }
+        Some(Command::Reset { checklist, items }) => {
+            commands::reset::run(&store, checklist.path()?, checklist.name.as_deref(), items)
+        }
+        Some(Command::Check { items: args, skip }) => commands::check::run(
+            &store,
+            args.checklist.path()?,
+            args.checklist.name.as_deref(),
+            &args.item,
+            true,
+            *skip,
+        ),
+        Some(Command::Uncheck(args)) => commands::check::run(
+            &store,
+            args.checklist.path()?,
+            args.checklist.name.as_deref(),
+            &args.item,
+            false,
+            false,
+        ),
+        Some(Command::Trailers {
+            checklist,
+            message_file,
+        }) => commands::trailers::run(
+            &store,
+            checklist.path()?,
+            checklist.name.as_deref(),
+            message_file,
+        ),
+        Some(Command::VerifyCommit { checklist, rev }) => commands::verify_commit::run(
+            rev,
+            checklist.checklist_path.as_ref(),
+            checklist.name.as_deref(),
+        ),
+        Some(Command::Gc { checklist_path }) => {
+            commands::gc::run(&store, checklist_path_given(checklist_path)?)
+        }
+        Some(Command::Hooks { action }) => match action {
+            HooksAction::Install {
+                checklist,
+                save,
+                hooks,
+            } => commands::hooks::install(
+                &config,
+                hooks,
+                *save,
+                args.profile.as_deref(),
+                checklist.checklist_path.as_deref(),
+                checklist.name.as_deref(),
+            ),
+            HooksAction::Uninstall { hooks } => commands::hooks::uninstall(hooks),
+            HooksAction::List => commands::hooks::list(),
+        },
+        None => run_checklist(&args, &store, checklist_path_given(&args.checklist_path)?),
+    }
+}
+
+fn run_checklist(args: &Args, store: &Store, checklist_path: &String) -> Result<ExitCode> {
     let save_and_load = args.save;
     let headless_mode = args.headless;
 
-    let file_contents = fs::read_to_string(&args.checklist_path)?;
-    let mut checklist = Checklist::from_markdown(file_contents)?;
+    let mut checklist = Checklist::load(checklist_path, args.name.as_deref())?;
 
-    if save_and_load {
-        match load_saved_checklist(&args.checklist_path, &checklist) {
-            Ok(checklist_loaded) => checklist.merge_checklist(&checklist_loaded),
-            Err(error) => log::error!("Failed to load saved checklist: {error}"),
-        };
+    let head = git(&["rev-parse", "HEAD"]);
+
+    if save_and_load
+        && let Err(error) = store.load_progress(&mut checklist, checklist_path, head.as_deref())
+    {
+        log::error!("Failed to load saved checklist: {error}");
+    }
+
+    let checklist_before = checklist.clone();
+    let user = current_user();
+
+    if !args.no_run {
+        checklist.run_checks(Duration::from_secs(
+            args.run_timeout.unwrap_or(DEFAULT_RUN_TIMEOUT),
+        ));
     }
 
     if !headless_mode {
-        checklist = draw(checklist);
+        checklist = match resolve_ui_mode(args.ui.unwrap_or(UiMode::Auto)) {
+            // stdout is left to reports, the prompt talks on stderr
+            UiMode::Prompt => prompt(checklist, &user, &mut io::stdin().lock(), &mut io::stderr())
+                .map_err(|error| KneeboardError::io("stdin", error))?,
+            _ => draw(checklist, &user),
+        };
+    }
+    checklist.stamp_resolutions(&checklist_before, &user);
+    for text in checklist.skips_since(&checklist_before) {
+        eprintln!("Skipped {text:?} out of order");
     }
 
     if save_and_load {
-        match save_checklist(&checklist, &args.checklist_path) {
-            Ok(_) => log::info!("Saved Checklist progress to {}", &args.checklist_path),
+        checklist.commit = head;
+        match store.save_checklist(&checklist, checklist_path) {
+            Ok(_) => log::info!("Saved Checklist progress to {checklist_path}"),
             Err(error) => log::error!("Failed to save Checklist progress: {error}"),
         };
+        let record = RunRecord::new(&checklist_before, &checklist, user);
+        if let Err(error) = store.append_history(&record, checklist_path, &checklist) {
+            log::error!("Failed to record Checklist run: {error}");
+        }
+    }
+
+    if args.write_back {
+        match write_back_checklist(&checklist, checklist_path) {
+            Ok(_) => log::info!("Wrote Checklist progress to {checklist_path}"),
+            Err(error) => log::error!("Failed to write Checklist progress: {error}"),
+        };
+    }
+
+    if let Some(report) = &args.report {
+        write_report(&checklist, report)?;
     }
 
     Ok(ExitCode::from(
@@ -91,36 +537,40 @@ fn main_sub() -> Result<ExitCode> {
     ))
 }
 
-fn get_save_file_name(checklist_name: &String) -> String {
-    let checklist_name_hash = xxh3_64(checklist_name.as_bytes());
-    format!("{checklist_name_hash:x}")
+/// Picks the prompt over the TUI unless stdin and stdout are a terminal the TUI can draw on,
+/// so redirected output like a report is never mixed with the checklist
+fn resolve_ui_mode(ui: UiMode) -> UiMode {
+    if ui != UiMode::Auto {
+        return ui;
+    }
+    let dumb_terminal = env::var("TERM").is_ok_and(|term| term == "dumb");
+    if io::stdin().is_terminal() && io::stdout().is_terminal() && !dumb_terminal {
+        UiMode::Tui
+    } else {
+        info!("No capable terminal found, asking about items line by line");
+        UiMode::Prompt
+    }
 }
 
-fn save_checklist(checklist: &Checklist, checklist_path: &String) -> Result<()> {
-    let checklist_as_toml = checklist.to_toml()?;
-    let checklist_path_dir = Path::new(checklist_path)
-        .parent()
-        .ok_or("Failed to find parent dir path")?;
-    let checklist_save_path =
-        checklist_path_dir.join(format!(".{}.kb.toml", get_save_file_name(&checklist.name)));
-    fs::write(&checklist_save_path, checklist_as_toml)?;
-    debug!(
-        "Save Checklist progress to {}",
-        &checklist_save_path.to_string_lossy()
-    );
+fn write_back_checklist(checklist: &Checklist, checklist_path: &String) -> Result<()> {
+    let file_contents = fs::read_to_string(checklist_path)
+        .map_err(|error| KneeboardError::io(checklist_path, error))?;
+    let updated_contents = checklist.write_back(&file_contents)?;
+    if updated_contents != file_contents {
+        fs::write(checklist_path, updated_contents)
+            .map_err(|error| KneeboardError::io(checklist_path, error))?;
+    }
     Ok(())
 }
 
-fn load_saved_checklist(checklist_path: &String, checklist: &Checklist) -> Result<Checklist> {
-    let checklist_path_dir = Path::new(checklist_path)
-        .parent()
-        .ok_or("Failed to find parent dir path")?;
-    let checklist_save_path =
-        checklist_path_dir.join(format!(".{}.kb.toml", get_save_file_name(&checklist.name)));
-    if checklist_save_path.exists() {
-        let file_contents = fs::read_to_string(checklist_save_path)?;
-        Checklist::from_toml(file_contents)
-    } else {
-        Err("No save file found".into())
+fn write_report(checklist: &Checklist, report: &ReportTarget) -> Result<()> {
+    let checklist_report = checklist.to_report(report.format)?;
+    match &report.path {
+        Some(path) => {
+            fs::write(path, checklist_report).map_err(|error| KneeboardError::io(path, error))?;
+            info!("Wrote {} report to {}", report.format, path.display());
+        }
+        None => println!("{checklist_report}"),
     }
+    Ok(())
 }
@@ -0,0 +1,423 @@
+mod notes;
+mod scope;
+
+use crate::checklist::{Checklist, History, Reset, RunRecord, SavedScope, local_now};
+use crate::error::{KneeboardError, Result};
+use crate::helpers::git::git_in;
+use log::{debug, info, warn};
+use serde::Deserialize;
+use std::{
+    env, fmt, fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use xxhash_rust::xxh3::xxh3_64;
+
+pub use notes::NotesKey;
+pub use scope::Scope;
+
+/// Where saved progress and run history are kept
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum StateLocation {
+    /// Next to the checklist
+    #[default]
+    Checklist,
+    /// `$XDG_STATE_HOME/kneeboard`, or `~/.local/state/kneeboard`
+    Xdg,
+    /// `kneeboard/` inside the git directory of the checklist's repository
+    Git,
+    Dir(PathBuf),
+    /// `refs/notes/kneeboard` on the current commit or branch instead of files
+    Notes(NotesKey),
+}
+
+/// Saves and loads the progress and run history of checklists
+#[derive(Clone, Debug, Default)]
+pub struct Store {
+    pub location: StateLocation,
+    pub scope: Scope,
+    /// Reset rule loaded progress expires by instead of the one the checklist sets
+    pub reset: Option<Reset>,
+}
+
+/// The part of a save file garbage collection looks at
+#[derive(Deserialize)]
+struct SavedScopeOnly {
+    #[serde(default)]
+    scope: Option<SavedScope>,
+}
+
+impl FromStr for StateLocation {
+    type Err = String;
+
+    fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
+        match input {
+            "checklist" => Ok(StateLocation::Checklist),
+            "xdg" => Ok(StateLocation::Xdg),
+            "git" => Ok(StateLocation::Git),
+            "notes" => Ok(StateLocation::Notes(NotesKey::Commit)),
+            "notes-branch" => Ok(StateLocation::Notes(NotesKey::Branch)),
+            _ => Err(format!(
+                "unknown state location {input:?}, expected checklist, xdg, git, notes or notes-branch"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for StateLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateLocation::Checklist => write!(f, "checklist"),
+            StateLocation::Xdg => write!(f, "xdg"),
+            StateLocation::Git => write!(f, "git"),
+            StateLocation::Dir(dir) => write!(f, "{}", dir.display()),
+            StateLocation::Notes(NotesKey::Commit) => write!(f, "notes"),
+            StateLocation::Notes(NotesKey::Branch) => write!(f, "notes-branch"),
+        }
+    }
+}
+
+fn get_save_file_name(checklist_name: &String) -> String {
+    let checklist_name_hash = xxh3_64(checklist_name.as_bytes());
+    format!("{checklist_name_hash:x}")
+}
+
+/// Progress is saved under the checklist's `key`, or else its canonical path and name so
+/// checklists sharing a name don't overwrite each other, followed by the branch or worktree
+/// when progress is kept apart by them
+fn get_save_key(
+    checklist_path: &String,
+    checklist: &Checklist,
+    name: &str,
+    scope: Option<&SavedScope>,
+) -> Result<String> {
+    let mut key = match &checklist.key {
+        Some(key) => key.clone(),
+        None => {
+            let canonical_path = fs::canonicalize(checklist_path)
+                .map_err(|error| KneeboardError::io(checklist_path, error))?;
+            format!("{}\n{name}", canonical_path.display())
+        }
+    };
+    if let Some(scope) = scope {
+        key = format!("{key}\n{}", scope.key());
+    }
+    Ok(get_save_file_name(&key))
+}
+
+fn get_checklist_dir(checklist_path: &String) -> Result<&Path> {
+    Path::new(checklist_path).parent().ok_or_else(|| {
+        KneeboardError::validation(format!("{checklist_path} has no parent directory"))
+    })
+}
+
+impl StateLocation {
+    /// The directory holding the state of the checklist at `checklist_path`
+    fn dir(&self, checklist_path: &String) -> Result<PathBuf> {
+        match self {
+            StateLocation::Checklist => Ok(get_checklist_dir(checklist_path)?.to_path_buf()),
+            StateLocation::Xdg => {
+                let state_home = env::var_os("XDG_STATE_HOME")
+                    .filter(|dir| !dir.is_empty())
+                    .map(PathBuf::from)
+                    .or_else(|| {
+                        env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state"))
+                    })
+                    .ok_or_else(|| {
+                        KneeboardError::config("neither $XDG_STATE_HOME nor $HOME is set")
+                    })?;
+                Ok(state_home.join("kneeboard"))
+            }
+            StateLocation::Git => {
+                let mut checklist_dir = get_checklist_dir(checklist_path)?;
+                if checklist_dir.as_os_str().is_empty() {
+                    checklist_dir = Path::new(".");
+                }
+                let git_dir = git_in(checklist_dir, &["rev-parse", "--absolute-git-dir"])
+                    .ok_or_else(|| {
+                        KneeboardError::config(format!(
+                            "{checklist_path} isn't in a git repository"
+                        ))
+                    })?;
+                Ok(PathBuf::from(git_dir).join("kneeboard"))
+            }
+            StateLocation::Dir(dir) => Ok(dir.clone()),
+            StateLocation::Notes(_) => Err(KneeboardError::config(
+                "progress kept in git notes has no directory",
+            )),
+        }
+    }
+}
+
+impl Store {
+    /// Progress kept in git notes is kept apart by branch with `notes-branch` instead of a scope
+    pub fn new(location: StateLocation, scope: Scope, reset: Option<Reset>) -> Result<Store> {
+        if matches!(location, StateLocation::Notes(_)) && scope != Scope::Shared {
+            return Err(KneeboardError::config(format!(
+                "progress kept in git notes can't be kept by {scope}, use notes-branch to keep it by branch"
+            )));
+        }
+        Ok(Store {
+            location,
+            scope,
+            reset,
+        })
+    }
+
+    fn get_save_path(&self, checklist_path: &String, checklist: &Checklist) -> Result<PathBuf> {
+        self.get_state_file(checklist_path, checklist, "kb.toml")
+    }
+
+    fn get_history_path(&self, checklist_path: &String, checklist: &Checklist) -> Result<PathBuf> {
+        self.get_state_file(checklist_path, checklist, "kb.history.toml")
+    }
+
+    /// The state file of the checklist ending in `extension`. Shared files saved under the old
+    /// name-only key, or under the name older versions gave the checklist, are moved to the new
+    /// key the first time they are used
+    fn get_state_file(
+        &self,
+        checklist_path: &String,
+        checklist: &Checklist,
+        extension: &str,
+    ) -> Result<PathBuf> {
+        let dir = self.location.dir(checklist_path)?;
+        let scope = self.scope.current(checklist_path)?;
+        let path = dir.join(format!(
+            ".{}.{extension}",
+            get_save_key(checklist_path, checklist, &checklist.name, scope.as_ref())?
+        ));
+        if path.exists() || scope.is_some() {
+            return Ok(path);
+        }
+        let legacy_name = checklist.legacy_name();
+        let legacy_keys = [
+            get_save_key(checklist_path, checklist, &legacy_name, None)?,
+            get_save_file_name(&checklist.name),
+            get_save_file_name(&legacy_name),
+        ];
+        let Some(legacy_path) = legacy_keys
+            .iter()
+            .map(|key| dir.join(format!(".{key}.{extension}")))
+            .find(|legacy_path| legacy_path.exists())
+        else {
+            return Ok(path);
+        };
+        match fs::rename(&legacy_path, &path) {
+            Ok(()) => {
+                info!("Moved {} to {}", legacy_path.display(), path.display());
+                Ok(path)
+            }
+            Err(error) => {
+                warn!(
+                    "Failed to move {} to {}, reading it in place: {error}",
+                    legacy_path.display(),
+                    path.display()
+                );
+                Ok(legacy_path)
+            }
+        }
+    }
+
+    pub fn save_checklist(&self, checklist: &Checklist, checklist_path: &String) -> Result<()> {
+        if let StateLocation::Notes(key) = self.location {
+            notes::save_checklist(key, checklist, checklist_path)?;
+            self.collect_garbage_after_save(checklist_path);
+            return Ok(());
+        }
+        let checklist_save_path = self.get_save_path(checklist_path, checklist)?;
+        let checklist = Checklist {
+            scope: self.scope.current(checklist_path)?,
+            ..checklist.clone()
+        };
+        let checklist_as_toml = checklist
+            .to_toml()
+            .map_err(|error| KneeboardError::save_file(&checklist_save_path, error))?;
+        create_parent_dir(&checklist_save_path)?;
+        fs::write(&checklist_save_path, checklist_as_toml).map_err(|error| {
+            KneeboardError::save_file(
+                &checklist_save_path,
+                KneeboardError::io(&checklist_save_path, error),
+            )
+        })?;
+        debug!(
+            "Save Checklist progress to {}",
+            &checklist_save_path.to_string_lossy()
+        );
+        self.collect_garbage_after_save(checklist_path);
+        Ok(())
+    }
+
+    /// Progress kept apart by branch or worktree is cleaned up whenever progress is saved
+    fn collect_garbage_after_save(&self, checklist_path: &String) {
+        if self.scope == Scope::Shared && self.location != StateLocation::Notes(NotesKey::Branch) {
+            return;
+        }
+        match self.collect_garbage(checklist_path) {
+            Ok(removed) => {
+                for scope in removed {
+                    info!("Removed the saved progress of {scope}");
+                }
+            }
+            Err(error) => warn!("Failed to remove the saved progress of deleted branches: {error}"),
+        }
+    }
+
+    /// Removes the progress and history saved for branches and worktrees of the checklist's
+    /// repository that no longer exist, returning what they were saved for
+    pub fn collect_garbage(&self, checklist_path: &String) -> Result<Vec<String>> {
+        if let StateLocation::Notes(_) = self.location {
+            return notes::collect_garbage();
+        }
+        let mut dir = self.location.dir(checklist_path)?;
+        if dir.as_os_str().is_empty() {
+            dir = PathBuf::from(".");
+        }
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(KneeboardError::io(&dir, error)),
+        };
+        let git_dir = scope::git_dir_of(checklist_path);
+        let mut removed = Vec::new();
+        for entry in entries {
+            let save_path = entry
+                .map_err(|error| KneeboardError::io(&dir, error))?
+                .path();
+            let Some(file_name) = save_path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            let Some(key) = file_name
+                .strip_prefix('.')
+                .and_then(|name| name.strip_suffix(".kb.toml"))
+            else {
+                continue;
+            };
+            let saved = fs::read_to_string(&save_path)
+                .ok()
+                .and_then(|contents| toml::from_str::<SavedScopeOnly>(&contents).ok());
+            let Some(scope) = saved.and_then(|saved| saved.scope) else {
+                continue;
+            };
+            if !scope.is_stale(git_dir) {
+                continue;
+            }
+            let history_path = dir.join(format!(".{key}.kb.history.toml"));
+            for path in [&save_path, &history_path] {
+                match fs::remove_file(path) {
+                    Ok(()) => debug!("Removed {}", path.display()),
+                    Err(error) if error.kind() == io::ErrorKind::NotFound => (),
+                    Err(error) => return Err(KneeboardError::io(path, error)),
+                }
+            }
+            removed.push(scope.to_string());
+        }
+        Ok(removed)
+    }
+
+    /// The saved progress of the checklist, `None` when none was saved yet
+    pub fn load_saved_checklist(
+        &self,
+        checklist_path: &String,
+        checklist: &Checklist,
+    ) -> Result<Option<Checklist>> {
+        if let StateLocation::Notes(key) = self.location {
+            return notes::load_saved_checklist(key, checklist_path, checklist);
+        }
+        let checklist_save_path = self.get_save_path(checklist_path, checklist)?;
+        if !checklist_save_path.exists() {
+            return Ok(None);
+        }
+        let file_contents = fs::read_to_string(&checklist_save_path).map_err(|error| {
+            KneeboardError::save_file(
+                &checklist_save_path,
+                KneeboardError::io(&checklist_save_path, error),
+            )
+        })?;
+        Checklist::from_toml(file_contents)
+            .map(Some)
+            .map_err(|error| KneeboardError::save_file(&checklist_save_path, error))
+    }
+
+    /// Merges the saved progress of the checklist into it, dropping expired resolutions.
+    /// Returns `false` when no progress was saved yet
+    pub fn load_progress(
+        &self,
+        checklist: &mut Checklist,
+        checklist_path: &String,
+        head: Option<&str>,
+    ) -> Result<bool> {
+        let Some(checklist_loaded) = self.load_saved_checklist(checklist_path, checklist)? else {
+            debug!("No saved progress for {:?}", checklist.name);
+            return Ok(false);
+        };
+        // Progress landing on another item or going missing is shown whatever the verbosity
+        let report = checklist.merge_checklist(&checklist_loaded);
+        for (saved_text, text) in report.similar {
+            eprintln!("Saved progress for {saved_text:?} was carried over to {text:?}");
+        }
+        for text in report.unmatched {
+            eprintln!("Saved progress for {text:?} doesn't match any item and was dropped");
+        }
+        if let Some(reset) = self.reset {
+            checklist.set_reset(reset);
+        }
+        for text in checklist.expire_resolutions(head, local_now()) {
+            warn!("Saved progress for {text:?} has expired");
+        }
+        Ok(true)
+    }
+
+    /// Appends the run to the history file of the checklist, earlier runs are never rewritten
+    pub fn append_history(
+        &self,
+        record: &RunRecord,
+        checklist_path: &String,
+        checklist: &Checklist,
+    ) -> Result<()> {
+        if let StateLocation::Notes(key) = self.location {
+            return notes::append_history(key, record, checklist_path, checklist);
+        }
+        let history_path = self.get_history_path(checklist_path, checklist)?;
+        let entry = record
+            .to_toml()
+            .map_err(|error| KneeboardError::save_file(&history_path, error))?;
+        create_parent_dir(&history_path)?;
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&history_path)
+            .and_then(|mut file| writeln!(file, "{entry}"))
+            .map_err(|error| {
+                KneeboardError::save_file(&history_path, KneeboardError::io(&history_path, error))
+            })?;
+        debug!("Appended run to {}", history_path.display());
+        Ok(())
+    }
+
+    /// Loads the runs recorded for the checklist, a checklist that was never run has none
+    pub fn load_history(&self, checklist_path: &String, checklist: &Checklist) -> Result<History> {
+        if let StateLocation::Notes(key) = self.location {
+            return notes::load_history(key, checklist_path, checklist);
+        }
+        let history_path = self.get_history_path(checklist_path, checklist)?;
+        if !history_path.exists() {
+            return Ok(History::default());
+        }
+        let file_contents = fs::read_to_string(&history_path).map_err(|error| {
+            KneeboardError::save_file(&history_path, KneeboardError::io(&history_path, error))
+        })?;
+        History::from_toml(file_contents)
+            .map_err(|error| KneeboardError::save_file(&history_path, error))
+    }
+}
+
+/// State directories other than the checklist's own may not exist yet
+fn create_parent_dir(path: &Path) -> Result<()> {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => fs::create_dir_all(dir)
+            .map_err(|error| KneeboardError::save_file(path, KneeboardError::io(dir, error))),
+        _ => Ok(()),
+    }
+}
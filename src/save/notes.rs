@@ -0,0 +1,196 @@
+use super::scope::{branch_is_deleted, short_branch};
+use crate::checklist::{Checklist, History, RunRecord};
+use crate::error::{Format, KneeboardError, Result};
+use crate::helpers::git::{git, git_with_input, repo_relative_path};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, io, path::Path};
+
+/// The notes ref progress is kept in, push and fetch it to share progress
+pub const NOTES_REF: &str = "refs/notes/kneeboard";
+
+/// Start of the blob a branch's notes are attached to, followed by the branch
+const BRANCH_OBJECT_PREFIX: &str = "kneeboard progress of ";
+
+/// What the note holding the progress is attached to
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum NotesKey {
+    /// The commit `HEAD` points at, progress starts over on every new commit
+    #[default]
+    Commit,
+    /// The branch `HEAD` is on, through an object named after the branch
+    Branch,
+}
+
+/// Everything kept in one note, by checklist
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Note {
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    progress: BTreeMap<String, Checklist>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    history: BTreeMap<String, Vec<RunRecord>>,
+}
+
+impl NotesKey {
+    /// The object the note is attached to
+    fn object(&self) -> Result<String> {
+        match self {
+            NotesKey::Commit => {
+                git(&["rev-parse", "--verify", "--quiet", "HEAD"]).ok_or_else(|| {
+                    KneeboardError::config("progress kept in git notes needs a commit to go on")
+                })
+            }
+            NotesKey::Branch => {
+                let branch = git(&["symbolic-ref", "--quiet", "HEAD"]).ok_or_else(|| {
+                    KneeboardError::config("progress kept in git notes by branch needs a branch")
+                })?;
+                // Notes can only be attached to objects, so the branch gets a blob of its own
+                git_with_input(
+                    &["hash-object", "-w", "--stdin"],
+                    &format!("{BRANCH_OBJECT_PREFIX}{branch}\n"),
+                )
+                .filter(|object| !object.is_empty())
+                .ok_or_else(|| {
+                    KneeboardError::config(format!("failed to name an object after {branch}"))
+                })
+            }
+        }
+    }
+}
+
+/// What the checklist is kept under in a note, the same for everyone working on the repository
+fn note_key(checklist_path: &String, checklist: &Checklist) -> Result<String> {
+    match &checklist.key {
+        Some(key) => Ok(key.clone()),
+        None => {
+            let path = repo_relative_path(checklist_path)
+                .map_err(|error| KneeboardError::io(checklist_path, error))?;
+            Ok(format!("{path}:{}", checklist.name))
+        }
+    }
+}
+
+fn note_name(object: &str) -> String {
+    format!("{NOTES_REF}:{object}")
+}
+
+fn read_note(object: &str) -> Result<Note> {
+    let Some(contents) = git(&["notes", "--ref", NOTES_REF, "show", object]) else {
+        debug!("No note on {object}");
+        return Ok(Note::default());
+    };
+    toml::from_str::<Note>(&contents).map_err(|error| {
+        KneeboardError::save_file(note_name(object), KneeboardError::toml(&contents, error))
+    })
+}
+
+fn write_note(object: &str, note: &Note) -> Result<()> {
+    let contents = toml::to_string_pretty(note).map_err(|error| {
+        KneeboardError::save_file(
+            note_name(object),
+            KneeboardError::Serialize {
+                format: Format::Toml,
+                source: Box::new(error),
+            },
+        )
+    })?;
+    git_with_input(
+        &[
+            "notes", "--ref", NOTES_REF, "add", "--force", "--file", "-", object,
+        ],
+        &contents,
+    )
+    .ok_or_else(|| {
+        KneeboardError::io(
+            note_name(object),
+            io::Error::other("git notes failed to save the note"),
+        )
+    })?;
+    debug!("Saved note on {object}");
+    Ok(())
+}
+
+pub(super) fn load_saved_checklist(
+    key: NotesKey,
+    checklist_path: &String,
+    checklist: &Checklist,
+) -> Result<Option<Checklist>> {
+    let mut note = read_note(&key.object()?)?;
+    Ok(note.progress.remove(&note_key(checklist_path, checklist)?))
+}
+
+pub(super) fn save_checklist(
+    key: NotesKey,
+    checklist: &Checklist,
+    checklist_path: &String,
+) -> Result<()> {
+    let object = key.object()?;
+    let mut note = read_note(&object)?;
+    note.progress
+        .insert(note_key(checklist_path, checklist)?, checklist.clone());
+    write_note(&object, &note)
+}
+
+pub(super) fn append_history(
+    key: NotesKey,
+    record: &RunRecord,
+    checklist_path: &String,
+    checklist: &Checklist,
+) -> Result<()> {
+    let object = key.object()?;
+    let mut note = read_note(&object)?;
+    note.history
+        .entry(note_key(checklist_path, checklist)?)
+        .or_default()
+        .push(record.clone());
+    write_note(&object, &note)
+}
+
+pub(super) fn load_history(
+    key: NotesKey,
+    checklist_path: &String,
+    checklist: &Checklist,
+) -> Result<History> {
+    let mut note = read_note(&key.object()?)?;
+    Ok(History {
+        runs: note
+            .history
+            .remove(&note_key(checklist_path, checklist)?)
+            .unwrap_or_default(),
+    })
+}
+
+/// Removes the notes kept for branches that were deleted, returning what they were kept for
+pub(super) fn collect_garbage() -> Result<Vec<String>> {
+    let Some(list) = git(&["notes", "--ref", NOTES_REF, "list"]) else {
+        return Ok(Vec::new());
+    };
+    let mut removed = Vec::new();
+    for object in list
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+    {
+        if git(&["cat-file", "-t", object]).as_deref() != Some("blob") {
+            continue;
+        }
+        let Some(branch) = git(&["cat-file", "blob", object]).and_then(|contents| {
+            contents
+                .strip_prefix(BRANCH_OBJECT_PREFIX)
+                .map(String::from)
+        }) else {
+            continue;
+        };
+        if !branch_is_deleted(Path::new("."), &branch) {
+            continue;
+        }
+        git_with_input(&["notes", "--ref", NOTES_REF, "remove", object], "").ok_or_else(|| {
+            KneeboardError::io(
+                note_name(object),
+                io::Error::other("git notes failed to remove the note"),
+            )
+        })?;
+        debug!("Removed note on {object} for {branch}");
+        removed.push(format!("branch {}", short_branch(&branch)));
+    }
+    Ok(removed)
+}
@@ -0,0 +1,159 @@
+use crate::checklist::SavedScope;
+use crate::error::{KneeboardError, Result};
+use crate::helpers::git::git_in;
+use log::warn;
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+const BRANCH_PREFIX: &str = "refs/heads/";
+
+/// What saved progress is kept apart by
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Scope {
+    /// One saved progress for every branch and worktree
+    #[default]
+    Shared,
+    /// Progress of its own for the branch `HEAD` is on
+    Branch,
+    /// Progress of its own for every worktree of the repository
+    Worktree,
+}
+
+impl FromStr for Scope {
+    type Err = String;
+
+    fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
+        match input {
+            "shared" => Ok(Scope::Shared),
+            "branch" => Ok(Scope::Branch),
+            "worktree" => Ok(Scope::Worktree),
+            _ => Err(format!(
+                "unknown scope {input:?}, expected shared, branch or worktree"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scope::Shared => write!(f, "shared"),
+            Scope::Branch => write!(f, "branch"),
+            Scope::Worktree => write!(f, "worktree"),
+        }
+    }
+}
+
+impl fmt::Display for SavedScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.branch, &self.worktree) {
+            (Some(branch), _) => write!(f, "branch {}", short_branch(branch)),
+            (None, Some(worktree)) => write!(f, "worktree {worktree}"),
+            (None, None) => write!(f, "shared"),
+        }
+    }
+}
+
+pub(super) fn short_branch(branch: &str) -> &str {
+    branch.strip_prefix(BRANCH_PREFIX).unwrap_or(branch)
+}
+
+/// The directory git runs in for the checklist at `checklist_path`
+pub(super) fn git_dir_of(checklist_path: &str) -> &Path {
+    match Path::new(checklist_path).parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    }
+}
+
+fn canonical(dir: &Path, path: &str) -> String {
+    let path = dir.join(path);
+    fs::canonicalize(&path)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// The git directory every worktree of the repository `dir` is in shares
+pub(super) fn repository_of(dir: &Path) -> Option<String> {
+    git_in(dir, &["rev-parse", "--git-common-dir"]).map(|common_dir| canonical(dir, &common_dir))
+}
+
+impl Scope {
+    /// The branch or worktree the checklist at `checklist_path` is on, `None` when progress is
+    /// shared. A detached `HEAD` has no branch and shares progress
+    pub fn current(&self, checklist_path: &str) -> Result<Option<SavedScope>> {
+        let dir = git_dir_of(checklist_path);
+        let (branch, worktree) = match self {
+            Scope::Shared => return Ok(None),
+            Scope::Branch => (git_in(dir, &["symbolic-ref", "--quiet", "HEAD"]), None),
+            Scope::Worktree => (
+                None,
+                git_in(dir, &["rev-parse", "--show-toplevel"])
+                    .map(|worktree| canonical(dir, &worktree)),
+            ),
+        };
+        let repository = repository_of(dir).ok_or_else(|| {
+            KneeboardError::config(format!(
+                "progress kept by {self} needs {checklist_path} to be in a git repository"
+            ))
+        })?;
+        if branch.is_none() && worktree.is_none() {
+            warn!("HEAD isn't on a {self}, using the shared progress");
+            return Ok(None);
+        }
+        Ok(Some(SavedScope {
+            repository,
+            branch,
+            worktree,
+        }))
+    }
+}
+
+impl SavedScope {
+    /// What is added to the save key to keep the progress apart
+    pub(super) fn key(&self) -> String {
+        match (&self.branch, &self.worktree) {
+            (Some(branch), _) => branch.clone(),
+            (None, Some(worktree)) => worktree.clone(),
+            (None, None) => String::new(),
+        }
+    }
+
+    /// Whether the branch or worktree is gone from the repository at `dir`. Progress saved for
+    /// another repository is never stale, it can't be told from here
+    pub(super) fn is_stale(&self, dir: &Path) -> bool {
+        if repository_of(dir).as_ref() != Some(&self.repository) {
+            return false;
+        }
+        if let Some(branch) = &self.branch {
+            return branch_is_deleted(dir, branch);
+        }
+        if let Some(worktree) = &self.worktree {
+            return !worktrees(dir).contains(worktree);
+        }
+        false
+    }
+}
+
+/// Whether `branch` is gone, the branch `HEAD` is on counts even before its first commit
+pub(super) fn branch_is_deleted(dir: &Path, branch: &str) -> bool {
+    git_in(dir, &["rev-parse", "--verify", "--quiet", branch]).is_none()
+        && git_in(dir, &["symbolic-ref", "--quiet", "HEAD"]).as_deref() != Some(branch)
+}
+
+/// The canonical paths of the worktrees of the repository at `dir` that still exist
+fn worktrees(dir: &Path) -> Vec<String> {
+    let Some(list) = git_in(dir, &["worktree", "list", "--porcelain"]) else {
+        return Vec::new();
+    };
+    list.lines()
+        .filter_map(|line| line.strip_prefix("worktree "))
+        .map(PathBuf::from)
+        .filter(|worktree| worktree.exists())
+        .map(|worktree| canonical(dir, &worktree.to_string_lossy()))
+        .collect()
+}
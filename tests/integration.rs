@@ -1,9 +1,77 @@
+use std::ffi::OsStr;
 use std::fs;
-use std::path::Path;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
 
 use assert_cmd::cargo::cargo_bin_cmd;
 use predicates::prelude::*;
 
+/// A test checklist in a directory of its own, the directory is removed when it is dropped
+struct TempChecklist(PathBuf);
+
+impl Deref for TempChecklist {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempChecklist {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<OsStr> for TempChecklist {
+    fn as_ref(&self) -> &OsStr {
+        self.0.as_os_str()
+    }
+}
+
+impl Drop for TempChecklist {
+    fn drop(&mut self) {
+        if let Some(temp_dir) = self.0.parent() {
+            let _ = fs::remove_dir_all(temp_dir);
+        }
+    }
+}
+
+/// Copies a test checklist into a fresh directory so tests can modify it freely
+fn copy_to_temp_dir(test_name: &str, checklist_path: &str) -> TempChecklist {
+    let temp_dir =
+        std::env::temp_dir().join(format!("kneeboard-{test_name}-{}", std::process::id()));
+    if temp_dir.exists() {
+        fs::remove_dir_all(&temp_dir).expect("failed to clean temp dir");
+    }
+    fs::create_dir_all(&temp_dir).expect("failed to create temp dir");
+    let checklist_path = Path::new(checklist_path);
+    let temp_checklist_path = temp_dir.join(checklist_path.file_name().unwrap());
+    fs::copy(checklist_path, &temp_checklist_path).expect("failed to copy checklist");
+    TempChecklist(temp_checklist_path)
+}
+
+/// Runs git in `dir` as a test user with the kneeboard under test on `PATH`, for the hooks it
+/// installs. Returns stdout, failing the test when git fails
+fn git_in(dir: &Path, args: &[&str]) -> String {
+    let bin_dir = assert_cmd::cargo::cargo_bin!("kneeboard").parent().unwrap();
+    let path = std::env::join_paths(
+        std::iter::once(bin_dir.to_path_buf())
+            .chain(std::env::split_paths(&std::env::var_os("PATH").unwrap())),
+    )
+    .unwrap();
+    let output = std::process::Command::new("git")
+        .current_dir(dir)
+        .env("PATH", path)
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .stdin(std::process::Stdio::null())
+        .output()
+        .expect("failed to run git");
+    assert!(output.status.success(), "git {args:?} failed: {output:?}");
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
 #[test]
 fn help_command() {
     let mut cmd = cargo_bin_cmd!("kneeboard");
@@ -13,17 +81,104 @@ fn help_command() {
         .stderr("")
         .stdout(predicate::str::contains("Checklist Tool"))
         .stdout(predicate::str::contains(
-            "Usage: kneeboard [OPTIONS] --checklist-path <CHECKLIST_PATH>",
+            "Usage: kneeboard [OPTIONS] [COMMAND]",
+        ))
+        .stdout(predicate::str::contains("Commands:"))
+        .stdout(predicate::str::contains(
+            "lint           Check checklist files against SPEC.md",
+        ))
+        .stdout(predicate::str::contains(
+            "history        List the recorded runs of a checklist or show one of them",
+        ))
+        .stdout(predicate::str::contains(
+            "status         Print the saved progress of a checklist and its outstanding items",
+        ))
+        .stdout(predicate::str::contains(
+            "reset          Clear the saved progress of all or the given items",
+        ))
+        .stdout(predicate::str::contains(
+            "check          Resolve items in the saved progress without the TUI",
+        ))
+        .stdout(predicate::str::contains(
+            "uncheck        Unresolve items in the saved progress without the TUI",
+        ))
+        .stdout(predicate::str::contains(
+            "trailers       Add checklist trailers for the saved progress to a commit message",
+        ))
+        .stdout(predicate::str::contains(
+            "verify-commit  Check a commit has checklist trailers with every required item resolved",
+        ))
+        .stdout(predicate::str::contains(
+            "gc             Remove the saved progress of deleted branches and worktrees",
+        ))
+        .stdout(predicate::str::contains(
+            "hooks          Install, uninstall or list the git hooks running a checklist",
         ))
         .stdout(predicate::str::contains("Options:"))
         .stdout(predicate::str::contains(
             "-c, --checklist-path <CHECKLIST_PATH>",
         ))
         .stdout(predicate::str::contains("Path to the checklist"))
+        .stdout(predicate::str::contains("-p, --profile <NAME>"))
+        .stdout(predicate::str::contains(
+            "Profile from kneeboard.toml to run, default unless given",
+        ))
+        .stdout(predicate::str::contains("-n, --name <NAME>"))
+        .stdout(predicate::str::contains(
+            "Name of the checklist to use from the file",
+        ))
         .stdout(predicate::str::contains("-s, --save"))
         .stdout(predicate::str::contains(
             "Save and load progress of the checklist",
         ))
+        .stdout(predicate::str::contains("--no-save"))
+        .stdout(predicate::str::contains(
+            "Don't save progress, even when the profile does",
+        ))
+        .stdout(predicate::str::contains("--no-write-back"))
+        .stdout(predicate::str::contains(
+            "Don't write progress back, even when the profile does",
+        ))
+        .stdout(predicate::str::contains("--no-headless"))
+        .stdout(predicate::str::contains(
+            "Ask about items, even when the profile is headless",
+        ))
+        .stdout(predicate::str::contains("--run "))
+        .stdout(predicate::str::contains(
+            "Run item commands, even when the profile skips them",
+        ))
+        .stdout(predicate::str::contains("--write-back"))
+        .stdout(predicate::str::contains(
+            "Write progress back into the checklist file",
+        ))
+        .stdout(predicate::str::contains("--report <FORMAT[=PATH]>"))
+        .stdout(predicate::str::contains(
+            "Write a report of the run, to stdout unless a path is given",
+        ))
+        .stdout(predicate::str::contains("--ui <MODE>"))
+        .stdout(predicate::str::contains(
+            "Ask with auto, tui or prompt [default: auto]",
+        ))
+        .stdout(predicate::str::contains("--no-run"))
+        .stdout(predicate::str::contains("Skip running item commands"))
+        .stdout(predicate::str::contains("--run-timeout <SECONDS>"))
+        .stdout(predicate::str::contains(
+            "Seconds before an item command times out [default: 300]",
+        ))
+        .stdout(predicate::str::contains("--state <LOCATION>"))
+        .stdout(predicate::str::contains(
+            "Keep progress in checklist, xdg, git, notes or notes-branch",
+        ))
+        .stdout(predicate::str::contains("--state-dir <DIR>"))
+        .stdout(predicate::str::contains("Keep progress in this directory"))
+        .stdout(predicate::str::contains("--scope <SCOPE>"))
+        .stdout(predicate::str::contains(
+            "Keep progress shared, by branch or by worktree",
+        ))
+        .stdout(predicate::str::contains("--reset-rule <RULE>"))
+        .stdout(predicate::str::contains(
+            "Expire saved progress daily or on-new-commit instead",
+        ))
         .stdout(predicate::str::contains("-v, --verbose..."))
         .stdout(predicate::str::contains("Turn debugging information on"))
         .stdout(predicate::str::contains("-h, --help"))
@@ -48,10 +203,7 @@ fn loading_test_checklist() {
 
 #[test]
 fn loading_test_checklist_and_saving() {
-    let save_path = Path::new("__test__/test_checklists/.5bdafbac94e71e2f.kb.toml");
-    if save_path.exists() {
-        fs::remove_file(save_path).expect("failed to remove existing save file");
-    }
+    let checklist_path = copy_to_temp_dir("saving", "__test__/test_checklists/test2.md");
 
     let mut cmd = cargo_bin_cmd!("kneeboard");
     let assert = cmd
@@ -59,16 +211,17 @@ fn loading_test_checklist_and_saving() {
         .arg("--save")
         .arg("--headless")
         .arg("--checklist-path")
-        .arg("__test__/test_checklists/test2.md")
+        .arg(&checklist_path)
         .assert();
     assert.code(7);
 
-    let save_path = Path::new("__test__/test_checklists/.5bdafbac94e71e2f.kb.toml");
-    assert!(
-        save_path.exists(),
-        "expected save file does not exist: {:?}",
-        save_path
+    let save_files = save_files_in(checklist_path.parent().unwrap());
+    assert_eq!(
+        save_files.len(),
+        1,
+        "expected one save file: {save_files:?}"
     );
+    let save_path = checklist_path.with_file_name(&save_files[0]);
 
     let content = fs::read_to_string(save_path).expect("failed to read saved toml file");
     let value: toml::Value = toml::from_str(&content).expect("invalid toml in save file");
@@ -83,13 +236,19 @@ fn loading_test_checklist_and_saving() {
         .get("items")
         .and_then(|v| v.as_array())
         .expect("missing or non-array 'items' in toml");
-    assert_eq!(items.len(), 8);
+    assert_eq!(items.len(), 6);
 
     let first_text = items[0]
         .get("text")
         .and_then(|v| v.as_str())
         .expect("missing or non-string 'text' on first item");
     assert_eq!(first_text, "Test Checklist 1 normal item");
+
+    let nested_items = items[2]
+        .get("children")
+        .and_then(|v| v.as_array())
+        .expect("missing or non-array 'children' on optional item");
+    assert_eq!(nested_items.len(), 2);
 }
 
 #[test]
@@ -97,13 +256,1437 @@ fn loading_test_checklist_and_saving_differing_save_and_mark() {
     // For this test the save file is modified to contain less items than the original checklist
     // This should trigger the merge logic to add the missing items
     // Exit code should be 10
+    let checklist_path = copy_to_temp_dir("differing", "__test__/test_checklists/test3.md");
+    // The save file is named after the checklist name only, as saved by older versions
+    let legacy_save_path = checklist_path.with_file_name(".21f98c40259cb4b4.kb.toml");
+    fs::copy(
+        "__test__/test_checklists/.21f98c40259cb4b4.kb.toml",
+        &legacy_save_path,
+    )
+    .expect("failed to copy save file");
+
     let mut cmd = cargo_bin_cmd!("kneeboard");
     let assert = cmd
         .arg("-vvv")
         .arg("--save")
         .arg("--headless")
         .arg("--checklist-path")
-        .arg("__test__/test_checklists/test3.md")
+        .arg(&checklist_path)
         .assert();
     assert.code(10);
+
+    // The old save file was moved to the key of the checklist's path and name
+    assert!(!legacy_save_path.exists());
+    let save_files = save_files_in(checklist_path.parent().unwrap());
+    assert_eq!(
+        save_files.len(),
+        1,
+        "expected one save file: {save_files:?}"
+    );
+}
+
+#[test]
+fn saving_checklists_with_the_same_name_in_one_directory() {
+    let checklist_path = copy_to_temp_dir("same-name", "__test__/test_checklists/test5.md");
+    let other_checklist_path = checklist_path.with_file_name("other.md");
+    fs::copy(&checklist_path, &other_checklist_path).unwrap();
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    cmd.arg("check")
+        .arg("--checklist-path")
+        .arg(&checklist_path)
+        .arg("--item")
+        .arg("normal item")
+        .assert()
+        .success();
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .arg("status")
+        .arg("--checklist-path")
+        .arg(&other_checklist_path)
+        .assert();
+    assert.code(3).stdout(predicate::str::contains(
+        "No progress saved for \"Test Checklist 5\"",
+    ));
+
+    // A shared key makes both files use the same progress
+    for path in [&*checklist_path, &other_checklist_path] {
+        let checklist = fs::read_to_string(path).unwrap().replacen(
+            "<!-- checklist = 'Test Checklist 5' -->",
+            "<!-- checklist = 'Test Checklist 5'\nkey = 'test-5' -->",
+            1,
+        );
+        fs::write(path, checklist).unwrap();
+    }
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    cmd.arg("check")
+        .arg("--checklist-path")
+        .arg(&checklist_path)
+        .arg("--item")
+        .arg("normal item")
+        .assert()
+        .success();
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .arg("status")
+        .arg("--checklist-path")
+        .arg(&other_checklist_path)
+        .assert();
+    assert.code(2).stdout(predicate::str::contains(
+        "Test Checklist 5: 1/3 resolved, 2 unresolved",
+    ));
+}
+
+#[test]
+fn loading_named_checklist() {
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .arg("--headless")
+        .arg("--checklist-path")
+        .arg("__test__/test_checklists/test4.md")
+        .arg("--name")
+        .arg("release")
+        .assert();
+    assert.code(4);
+
+    for (name, unresolved) in [("pre-commit", 2), ("pre-push", 3)] {
+        let mut cmd = cargo_bin_cmd!("kneeboard");
+        let assert = cmd
+            .arg("--headless")
+            .arg("--checklist-path")
+            .arg("__test__/test_checklists/test4.md")
+            .arg("--name")
+            .arg(name)
+            .assert();
+        assert.code(unresolved);
+    }
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .arg("--headless")
+        .arg("--checklist-path")
+        .arg("__test__/test_checklists/test4.md")
+        .assert();
+    assert.code(2);
+}
+
+#[test]
+fn loading_missing_named_checklist() {
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .arg("--headless")
+        .arg("--checklist-path")
+        .arg("__test__/test_checklists/test4.md")
+        .arg("--name")
+        .arg("missing")
+        .assert();
+    assert.failure().code(1);
+}
+
+#[test]
+fn loading_test_checklist_and_writing_back() {
+    let checklist_path = copy_to_temp_dir("write-back", "__test__/test_checklists/test3.md");
+    fs::copy(
+        "__test__/test_checklists/.21f98c40259cb4b4.kb.toml",
+        checklist_path.with_file_name(".21f98c40259cb4b4.kb.toml"),
+    )
+    .expect("failed to copy save file");
+    let save_path = checklist_path.with_file_name(".21f98c40259cb4b4.kb.toml");
+    let save_file =
+        fs::read_to_string(&save_path)
+            .unwrap()
+            .replacen("resolved = false", "resolved = true", 1);
+    fs::write(&save_path, save_file).unwrap();
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .arg("--save")
+        .arg("--write-back")
+        .arg("--headless")
+        .arg("--checklist-path")
+        .arg(&checklist_path)
+        .assert();
+    assert.code(9);
+
+    let original = fs::read_to_string("__test__/test_checklists/test3.md").unwrap();
+    let content = fs::read_to_string(&checklist_path).unwrap();
+    assert_eq!(
+        content,
+        original.replacen(
+            "- [ ] Test Checklist 1 normal item",
+            "- [x] Test Checklist 1 normal item",
+            1
+        )
+    );
+}
+
+#[test]
+fn loading_test_checklist_with_json_report() {
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .arg("--headless")
+        .arg("--report")
+        .arg("json")
+        .arg("--checklist-path")
+        .arg("__test__/test_checklists/test1.md")
+        .assert();
+    let output = assert.code(7).get_output().stdout.clone();
+
+    let value: serde_json::Value = serde_json::from_slice(&output).expect("invalid json in report");
+    assert_eq!(value["name"], "Test Checklist 1");
+    assert_eq!(value["items"].as_array().unwrap().len(), 6);
+    assert_eq!(value["totals"]["total"], 8);
+    assert_eq!(value["totals"]["unresolved"], 7);
+}
+
+#[test]
+fn prompt_leaves_stdout_to_the_report() {
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .arg("--report")
+        .arg("json")
+        .arg("--checklist-path")
+        .arg("__test__/test_checklists/test3.md")
+        .write_stdin("")
+        .assert();
+    let output = assert
+        .stderr(predicate::str::contains("[1/11]"))
+        .get_output()
+        .stdout
+        .clone();
+
+    let value: serde_json::Value = serde_json::from_slice(&output).expect("invalid json in report");
+    assert_eq!(value["name"], "Test Checklist 3");
+}
+
+#[test]
+fn loading_test_checklist_with_json_report_to_file() {
+    let checklist_path = copy_to_temp_dir("json-report", "__test__/test_checklists/test1.md");
+    let report_path = checklist_path.with_file_name("report.json");
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .arg("--headless")
+        .arg("--report")
+        .arg(format!("json={}", report_path.display()))
+        .arg("--checklist-path")
+        .arg(&checklist_path)
+        .assert();
+    assert.code(7).stdout("");
+
+    let content = fs::read_to_string(&report_path).expect("failed to read report");
+    let value: serde_json::Value = serde_json::from_str(&content).expect("invalid json in report");
+    assert_eq!(value["totals"]["unresolved"], 7);
+}
+
+#[test]
+fn loading_test_checklist_with_junit_and_tap_reports() {
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .arg("--headless")
+        .arg("--report")
+        .arg("junit")
+        .arg("--checklist-path")
+        .arg("__test__/test_checklists/test1.md")
+        .assert();
+    assert.code(7).stdout(predicate::str::contains(
+        r#"<testsuite name="Test Checklist 1" tests="8" failures="7" skipped="1">"#,
+    ));
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .arg("--headless")
+        .arg("--report")
+        .arg("tap")
+        .arg("--checklist-path")
+        .arg("__test__/test_checklists/test1.md")
+        .assert();
+    assert
+        .code(7)
+        .stdout(predicate::str::starts_with("TAP version 13\n1..8\n"))
+        .stdout(predicate::str::contains(
+            "ok 3 - Test Checklist 1 optional item [OPTIONAL] # SKIP",
+        ));
+}
+
+#[test]
+fn loading_missing_checklist_reports_path() {
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .arg("--headless")
+        .arg("--checklist-path")
+        .arg("__test__/test_checklists/missing.md")
+        .assert();
+    assert.code(1).stderr(predicate::str::contains(
+        "failed to access __test__/test_checklists/missing.md",
+    ));
+}
+
+#[test]
+fn lint_command() {
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .arg("lint")
+        .arg("__test__/test_checklists/test4.md")
+        .arg("__test__/test_checklists/lint.md")
+        .assert();
+    assert
+        .code(4)
+        .stdout(predicate::str::contains(
+            "__test__/test_checklists/lint.md:5:1: malformed checkbox",
+        ))
+        .stdout(predicate::str::contains(
+            "__test__/test_checklists/lint.md:6:1: duplicate item \"Lint item\", first defined on line 4",
+        ))
+        .stdout(predicate::str::contains("test4.md").not());
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .arg("lint")
+        .arg("__test__/test_checklists/test4.md")
+        .assert();
+    assert.success().stdout("");
+}
+
+#[test]
+fn loading_test_checklist_and_saving_reports_unmatched_saved_items() {
+    let checklist_path = copy_to_temp_dir("unmatched", "__test__/test_checklists/test3.md");
+    let save_file = fs::read_to_string("__test__/test_checklists/.21f98c40259cb4b4.kb.toml")
+        .unwrap()
+        .replace(
+            "text = \"Test Checklist 1 normal item\"\noptional = false\nresolved = false",
+            "text = \"Test Checklst 1 normal item\"\noptional = false\nresolved = true",
+        )
+        + "\n[[items]]\ntext = \"Removed item\"\noptional = false\nresolved = true\n";
+    fs::write(
+        checklist_path.with_file_name(".21f98c40259cb4b4.kb.toml"),
+        save_file,
+    )
+    .unwrap();
+
+    // Shown without -v, a user has to know when progress moves or goes away
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .arg("--save")
+        .arg("--headless")
+        .arg("--checklist-path")
+        .arg(&checklist_path)
+        .assert();
+    assert
+        .code(9)
+        .stderr(predicate::str::contains(
+            "Saved progress for \"Test Checklst 1 normal item\" was carried over to \"Test Checklist 1 normal item\"",
+        ))
+        .stderr(predicate::str::contains(
+            "Saved progress for \"Removed item\" doesn't match any item and was dropped",
+        ));
+}
+
+#[test]
+fn loading_test_checklist_runs_item_commands() {
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .arg("--headless")
+        .arg("--checklist-path")
+        .arg("__test__/test_checklists/test5.md")
+        .assert();
+    assert.code(2);
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .arg("--headless")
+        .arg("--no-run")
+        .arg("--checklist-path")
+        .arg("__test__/test_checklists/test5.md")
+        .assert();
+    assert.code(3);
+
+    // Commands that never ran leave their items to be answered by hand
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .args(["--no-run", "--ui", "prompt"])
+        .arg("--checklist-path")
+        .arg("__test__/test_checklists/test5.md")
+        .write_stdin("y\ny\nn\n")
+        .assert();
+    assert
+        .code(1)
+        .stderr(predicate::str::contains(
+            "[2/3] failing command? [y/n/skip/note] ",
+        ))
+        .stderr(predicate::str::contains("failed").not());
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .arg("--headless")
+        .arg("--report")
+        .arg("tap")
+        .arg("--checklist-path")
+        .arg("__test__/test_checklists/test5.md")
+        .assert();
+    assert
+        .code(2)
+        .stdout(predicate::str::contains("ok 1 - passing command"))
+        .stdout(predicate::str::contains("not ok 2 - failing command"));
+}
+
+#[test]
+fn history_command_lists_and_shows_runs() {
+    let checklist_path = copy_to_temp_dir("history", "__test__/test_checklists/test5.md");
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .arg("history")
+        .arg("--checklist-path")
+        .arg(&checklist_path)
+        .assert();
+    assert.success().stdout(predicate::str::contains(
+        "No runs recorded for \"Test Checklist 5\"",
+    ));
+
+    for _ in 0..2 {
+        let mut cmd = cargo_bin_cmd!("kneeboard");
+        let assert = cmd
+            .arg("--save")
+            .arg("--headless")
+            .arg("--checklist-path")
+            .arg(&checklist_path)
+            .assert();
+        assert.code(2);
+    }
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .arg("history")
+        .arg("--checklist-path")
+        .arg(&checklist_path)
+        .assert();
+    assert
+        .success()
+        .stdout(predicate::str::is_match("(?m)^1  .*  1 changed  2 unresolved$").unwrap())
+        .stdout(predicate::str::is_match("(?m)^2  .*  0 changed  2 unresolved$").unwrap());
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .arg("history")
+        .arg("--checklist-path")
+        .arg(&checklist_path)
+        .arg("1")
+        .assert();
+    assert
+        .success()
+        .stdout(predicate::str::contains("Run 1 of \"Test Checklist 5\""))
+        .stdout(predicate::str::contains("Unresolved: 2"))
+        .stdout(predicate::str::contains("  [x] passing command"));
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .arg("history")
+        .arg("--checklist-path")
+        .arg(&checklist_path)
+        .arg("3")
+        .assert();
+    assert.code(1);
+}
+
+#[test]
+fn loading_saved_checklist_expires_old_resolutions() {
+    let checklist_path = copy_to_temp_dir("expiry", "__test__/test_checklists/test6.md");
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    cmd.arg("--save")
+        .arg("--headless")
+        .arg("--checklist-path")
+        .arg(&checklist_path)
+        .assert()
+        .code(2);
+
+    let save_path = fs::read_dir(checklist_path.parent().unwrap())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| path.to_string_lossy().ends_with(".kb.toml"))
+        .unwrap();
+    let save_file = fs::read_to_string(&save_path).unwrap().replace(
+        "resolved = false",
+        "resolved = true\nresolved_at = \"2020-01-01T00:00:00Z\"\nresolved_by = \"alice\"",
+    );
+    fs::write(&save_path, save_file).unwrap();
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .arg("-v")
+        .arg("--save")
+        .arg("--headless")
+        .arg("--checklist-path")
+        .arg(&checklist_path)
+        .assert();
+    assert.code(1).stderr(predicate::str::contains(
+        "Saved progress for \"expiring item\" has expired",
+    ));
+}
+
+#[test]
+fn status_and_reset_commands() {
+    let checklist_path = copy_to_temp_dir("status", "__test__/test_checklists/test5.md");
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .arg("status")
+        .arg("--checklist-path")
+        .arg(&checklist_path)
+        .assert();
+    assert
+        .code(3)
+        .stdout(predicate::str::contains(
+            "No progress saved for \"Test Checklist 5\"",
+        ))
+        .stdout(predicate::str::contains(
+            "Test Checklist 5: 0/3 resolved, 3 unresolved",
+        ));
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    cmd.arg("--save")
+        .arg("--headless")
+        .arg("--checklist-path")
+        .arg(&checklist_path)
+        .assert()
+        .code(2);
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .arg("status")
+        .arg("--checklist-path")
+        .arg(&checklist_path)
+        .assert();
+    assert.code(2).stdout(
+        "Test Checklist 5: 1/3 resolved, 2 unresolved\nOutstanding:\n  [ ] failing command\n  [ ] normal item\n",
+    );
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .arg("reset")
+        .arg("--checklist-path")
+        .arg(&checklist_path)
+        .arg("no such item")
+        .assert();
+    assert.code(1);
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .arg("reset")
+        .arg("--checklist-path")
+        .arg(&checklist_path)
+        .arg("passing command")
+        .assert();
+    assert
+        .success()
+        .stdout("Reset 1 items of \"Test Checklist 5\"\n");
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .arg("status")
+        .arg("--checklist-path")
+        .arg(&checklist_path)
+        .assert();
+    assert.code(3).stdout(predicate::str::contains(
+        "Test Checklist 5: 0/3 resolved, 3 unresolved",
+    ));
+}
+
+#[test]
+fn check_and_uncheck_commands() {
+    let checklist_path = copy_to_temp_dir("check", "__test__/test_checklists/test4.md");
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .arg("check")
+        .arg("--checklist-path")
+        .arg(&checklist_path)
+        .arg("--name")
+        .arg("release")
+        .arg("--item")
+        .arg("1")
+        .arg("--item")
+        .arg("*item 3")
+        .assert();
+    assert.success().stdout(
+        "Checked \"Test Checklist 4 release item\"\nChecked \"Test Checklist 4 release item 3\"\n",
+    );
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .arg("--headless")
+        .arg("--save")
+        .arg("--checklist-path")
+        .arg(&checklist_path)
+        .arg("--name")
+        .arg("release")
+        .assert();
+    assert.code(2);
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .arg("uncheck")
+        .arg("--checklist-path")
+        .arg(&checklist_path)
+        .arg("--name")
+        .arg("release")
+        .arg("--item")
+        .arg("Test Checklist 4 release item 3")
+        .assert();
+    assert
+        .success()
+        .stdout("Unchecked \"Test Checklist 4 release item 3\"\n");
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .arg("status")
+        .arg("--checklist-path")
+        .arg(&checklist_path)
+        .arg("--name")
+        .arg("release")
+        .assert();
+    assert.code(3);
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .arg("check")
+        .arg("--checklist-path")
+        .arg(&checklist_path)
+        .arg("--name")
+        .arg("release")
+        .arg("--item")
+        .arg("Test Checklist 4")
+        .assert();
+    assert.code(1);
+}
+
+#[test]
+fn prompt_mode_asks_about_each_item() {
+    let checklist_path = copy_to_temp_dir("prompt", "__test__/test_checklists/test4.md");
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .arg("--save")
+        .arg("--checklist-path")
+        .arg(&checklist_path)
+        .arg("--name")
+        .arg("release")
+        .write_stdin("y\nmaybe\nnote see TICKET-42\nn\nskip\n")
+        .assert();
+    assert
+        .code(3)
+        .stdout("")
+        .stderr(predicate::str::contains(
+            "[1/4] Test Checklist 4 release item? [y/n/skip/note] ",
+        ))
+        .stderr(predicate::str::contains("Answer y, n, skip or note <text>"))
+        .stderr(predicate::str::contains("Noted"))
+        .stderr(predicate::str::contains(
+            "[4/4] Test Checklist 4 release item 4? [y/n/skip/note] \n",
+        ));
+
+    let save_path = fs::read_dir(checklist_path.parent().unwrap())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| path.to_string_lossy().ends_with(".kb.toml"))
+        .unwrap();
+    assert!(
+        fs::read_to_string(save_path)
+            .unwrap()
+            .contains("note = \"see TICKET-42\"")
+    );
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .arg("--save")
+        .arg("--ui")
+        .arg("prompt")
+        .arg("--checklist-path")
+        .arg(&checklist_path)
+        .arg("--name")
+        .arg("release")
+        .write_stdin("y\ny\ny\n")
+        .assert();
+    assert
+        .success()
+        .stderr(predicate::str::contains("[1/4]").not());
+}
+
+#[test]
+fn prompt_records_skips_in_sequential_checklists() {
+    let checklist_path = copy_to_temp_dir("skips", "__test__/test_checklists/test5.md");
+    fs::write(
+        &checklist_path,
+        "<!-- checklist = 'deploy'\nmode = 'sequential' -->\n- [ ] smoke tests pass <!-- run: false -->\n- [ ] announce the deploy\n<!-- checklist = 'deploy' -->\n",
+    )
+    .unwrap();
+    let run = |input: &str| {
+        let mut cmd = cargo_bin_cmd!("kneeboard");
+        cmd.arg("--save")
+            .arg("--ui")
+            .arg("prompt")
+            .arg("--checklist-path")
+            .arg(&checklist_path)
+            .write_stdin(input)
+            .assert()
+    };
+
+    // A failed command holds up the rest of a sequential checklist
+    run("n\n").code(2).stderr(predicate::str::contains(
+        "Stopping, \"smoke tests pass\" has to pass first",
+    ));
+
+    run("skip\ny\n")
+        .code(1)
+        .stderr(predicate::str::contains(
+            "[1/2] smoke tests pass - failed `false`, skip it? [skip/n] ",
+        ))
+        .stderr(predicate::str::contains(
+            "Skipped \"smoke tests pass\" out of order",
+        ));
+    let dir = checklist_path.parent().unwrap();
+    let save_file = fs::read_to_string(dir.join(&save_files_in(dir)[0])).unwrap();
+    assert!(save_file.contains("skipped_at = "));
+    assert!(save_file.contains("skipped_by = "));
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    cmd.arg("history")
+        .arg("--checklist-path")
+        .arg(&checklist_path)
+        .arg("2")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Skipped out of order:\n  smoke tests pass\n",
+        ));
+}
+
+#[test]
+fn check_keeps_sequential_checklists_in_order() {
+    let checklist_path = copy_to_temp_dir("check-order", "__test__/test_checklists/test5.md");
+    fs::write(
+        &checklist_path,
+        "<!-- checklist = 'deploy'\nmode = 'sequential' -->\n- [ ] step 1\n- [ ] step 2\n- [ ] step 3\n<!-- checklist = 'deploy' -->\n",
+    )
+    .unwrap();
+    let check = |args: &[&str]| {
+        let mut cmd = cargo_bin_cmd!("kneeboard");
+        cmd.arg("check")
+            .arg("--checklist-path")
+            .arg(&checklist_path)
+            .args(args)
+            .assert()
+    };
+
+    check(&["--item", "step 3"])
+        .failure()
+        .stderr(predicate::str::contains(
+            "open items come first in \"deploy\": \"step 1\", \"step 2\", pass --skip to skip them out of order",
+        ));
+    let dir = checklist_path.parent().unwrap();
+    assert!(save_files_in(dir).is_empty());
+
+    // Items picked together go in order
+    check(&["--item", "step 1", "--item", "step 2"]).success();
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    cmd.arg("uncheck")
+        .arg("--checklist-path")
+        .arg(&checklist_path)
+        .args(["--item", "step *"])
+        .assert()
+        .success();
+    check(&["--item", "step 2", "--skip"])
+        .success()
+        .stdout("Skipped \"step 1\" out of order\nChecked \"step 2\"\n");
+    let save_file = fs::read_to_string(dir.join(&save_files_in(dir)[0])).unwrap();
+    assert!(save_file.contains("skipped_by = "));
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    cmd.arg("history")
+        .arg("--checklist-path")
+        .arg(&checklist_path)
+        .arg("3")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Skipped out of order:\n  step 1\n",
+        ));
+}
+
+fn save_files_in(dir: &Path) -> Vec<String> {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+                .filter(|file_name| file_name.ends_with(".kb.toml"))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[test]
+fn saving_to_configured_state_dir() {
+    let checklist_path = copy_to_temp_dir("state-dir", "__test__/test_checklists/test5.md");
+    let checklist_dir = checklist_path.parent().unwrap();
+    let state_dir = checklist_dir.join("state");
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    cmd.arg("--save")
+        .arg("--headless")
+        .arg("--state-dir")
+        .arg(&state_dir)
+        .arg("--checklist-path")
+        .arg(&checklist_path)
+        .assert()
+        .code(2);
+    assert!(save_files_in(checklist_dir).is_empty());
+    assert_eq!(save_files_in(&state_dir).len(), 1);
+
+    // The environment picks the same directory for subcommands
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .env("KNEEBOARD_STATE_DIR", &state_dir)
+        .arg("status")
+        .arg("--checklist-path")
+        .arg(&checklist_path)
+        .assert();
+    assert.code(2).stdout(predicate::str::contains(
+        "Test Checklist 5: 1/3 resolved, 2 unresolved",
+    ));
+
+    // Flags win over the environment
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .env("KNEEBOARD_STATE_DIR", &state_dir)
+        .arg("status")
+        .arg("--state")
+        .arg("checklist")
+        .arg("--checklist-path")
+        .arg(&checklist_path)
+        .assert();
+    assert.code(3).stdout(predicate::str::contains(
+        "No progress saved for \"Test Checklist 5\"",
+    ));
+}
+
+#[test]
+fn saving_to_state_dir_from_config_file() {
+    let checklist_path = copy_to_temp_dir("config-state", "__test__/test_checklists/test5.md");
+    let checklist_dir = checklist_path.parent().unwrap();
+    fs::write(
+        checklist_dir.join("kneeboard.toml"),
+        "state-dir = 'progress'\n",
+    )
+    .unwrap();
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    cmd.current_dir(checklist_dir)
+        .env_remove("KNEEBOARD_STATE_DIR")
+        .env_remove("KNEEBOARD_STATE")
+        .arg("--save")
+        .arg("--headless")
+        .arg("--checklist-path")
+        .arg(&checklist_path)
+        .assert()
+        .code(2);
+    assert!(save_files_in(checklist_dir).is_empty());
+    assert_eq!(save_files_in(&checklist_dir.join("progress")).len(), 1);
+
+    fs::write(checklist_dir.join("kneeboard.toml"), "state = 'nowhere'\n").unwrap();
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .current_dir(checklist_dir)
+        .env_remove("KNEEBOARD_STATE_DIR")
+        .env_remove("KNEEBOARD_STATE")
+        .arg("status")
+        .arg("--checklist-path")
+        .arg(&checklist_path)
+        .assert();
+    assert.failure().stderr(predicate::str::contains(
+        "unknown state location \"nowhere\", expected checklist, xdg, git, notes or notes-branch",
+    ));
+}
+
+#[test]
+fn running_profiles_from_config_file() {
+    let checklist_path = copy_to_temp_dir("profiles", "__test__/test_checklists/test5.md");
+    let checklist_dir = checklist_path.parent().unwrap();
+    fs::write(
+        checklist_dir.join("kneeboard.toml"),
+        r#"
+[profiles.default]
+checklist = 'test5.md'
+headless = true
+no-run = true
+
+[profiles.release]
+checklist = 'test5.md'
+headless = true
+save = true
+report = 'json'
+"#,
+    )
+    .unwrap();
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    cmd.current_dir(checklist_dir).assert().code(3);
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .current_dir(checklist_dir)
+        .arg("--profile")
+        .arg("release")
+        .assert();
+    assert
+        .code(2)
+        .stdout(predicate::str::contains("\"name\": \"Test Checklist 5\""));
+    assert_eq!(save_files_in(checklist_dir).len(), 1);
+
+    // Subcommands take the checklist from the profile too
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .current_dir(checklist_dir)
+        .arg("status")
+        .arg("--profile")
+        .arg("release")
+        .assert();
+    assert.code(2).stdout(predicate::str::contains(
+        "Test Checklist 5: 1/3 resolved, 2 unresolved",
+    ));
+
+    // A checklist given before the subcommand wins over the profile
+    fs::copy(
+        "__test__/test_checklists/test1.md",
+        checklist_dir.join("test1.md"),
+    )
+    .unwrap();
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .current_dir(checklist_dir)
+        .args(["--checklist-path", "test1.md", "status"])
+        .assert();
+    assert.code(7).stdout(predicate::str::contains(
+        "Test Checklist 1: 0/8 resolved, 7 unresolved",
+    ));
+
+    // Flags turn the options of a profile off again
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    cmd.current_dir(checklist_dir).arg("--run").assert().code(2);
+    for save_file in save_files_in(checklist_dir) {
+        fs::remove_file(checklist_dir.join(save_file)).unwrap();
+    }
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    cmd.current_dir(checklist_dir)
+        .args(["--profile", "release", "--no-save"])
+        .assert()
+        .code(2);
+    assert!(save_files_in(checklist_dir).is_empty());
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    cmd.current_dir(checklist_dir)
+        .args(["--profile", "release", "--no-headless", "--ui", "prompt"])
+        .write_stdin("y\n")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("[3/3] normal item?"));
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .current_dir(checklist_dir)
+        .arg("--profile")
+        .arg("missing")
+        .assert();
+    assert
+        .failure()
+        .stderr(predicate::str::contains("no profile \"missing\" in"));
+}
+
+#[test]
+fn running_profile_from_xdg_config_home() {
+    let checklist_path = copy_to_temp_dir("xdg-config", "__test__/test_checklists/test5.md");
+    let checklist_dir = checklist_path.parent().unwrap();
+    let config_dir = checklist_dir.join("config").join("kneeboard");
+    fs::create_dir_all(&config_dir).unwrap();
+    fs::write(
+        config_dir.join("kneeboard.toml"),
+        format!(
+            "[profiles.default]\nchecklist = {:?}\nheadless = true\n",
+            checklist_path.to_string_lossy()
+        ),
+    )
+    .unwrap();
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    cmd.current_dir(checklist_dir)
+        .env("XDG_CONFIG_HOME", checklist_dir.join("config"))
+        .assert()
+        .code(2);
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .current_dir(checklist_dir)
+        .env("XDG_CONFIG_HOME", checklist_dir.join("missing"))
+        .env("HOME", checklist_dir)
+        .assert();
+    assert.failure().stderr(predicate::str::contains(
+        "no checklist given, pass --checklist-path or set a checklist in a profile",
+    ));
+}
+
+#[test]
+fn hooks_install_list_and_uninstall() {
+    let checklist_path = copy_to_temp_dir("hooks", "__test__/test_checklists/test5.md");
+    let repo_dir = checklist_path.parent().unwrap();
+    git_in(repo_dir, &["init", "-q"]);
+    let hooks_dir = repo_dir.join(".git").join("hooks");
+    fs::create_dir_all(&hooks_dir).unwrap();
+    fs::write(hooks_dir.join("pre-commit"), "#!/bin/sh\nexit 0\n").unwrap();
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .current_dir(repo_dir)
+        .arg("hooks")
+        .arg("install")
+        .arg("--checklist-path")
+        .arg("test5.md")
+        .arg("pre-commit")
+        .arg("pre-push")
+        .assert();
+    assert
+        .success()
+        .stdout(predicate::str::contains(
+            "Chained the existing pre-commit hook",
+        ))
+        .stdout(predicate::str::contains("Installed pre-push hook"));
+    let hook = fs::read_to_string(hooks_dir.join("pre-commit")).unwrap();
+    assert!(hook.contains("exec kneeboard --checklist-path test5.md\n"));
+    assert_eq!(
+        fs::read_to_string(hooks_dir.join("pre-commit.before-kneeboard")).unwrap(),
+        "#!/bin/sh\nexit 0\n"
+    );
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd.current_dir(repo_dir).arg("hooks").arg("list").assert();
+    assert
+        .success()
+        .stdout(predicate::str::contains(
+            "pre-commit          kneeboard --checklist-path test5.md, after the hook it replaced",
+        ))
+        .stdout(predicate::str::contains(
+            "pre-push            kneeboard --checklist-path test5.md\n",
+        ))
+        .stdout(predicate::str::contains(
+            "commit-msg          not installed",
+        ));
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .current_dir(repo_dir)
+        .arg("hooks")
+        .arg("uninstall")
+        .assert();
+    assert.success().stdout(predicate::str::contains(
+        "Uninstalled pre-commit hook and put back the hook it replaced",
+    ));
+    assert_eq!(
+        fs::read_to_string(hooks_dir.join("pre-commit")).unwrap(),
+        "#!/bin/sh\nexit 0\n"
+    );
+    assert!(!hooks_dir.join("pre-push").exists());
+
+    // Hooks go to core.hooksPath when it is set, and need a checklist to run
+    git_in(repo_dir, &["config", "core.hooksPath", "githooks"]);
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .current_dir(repo_dir)
+        .env("XDG_CONFIG_HOME", repo_dir.join("missing"))
+        .env("HOME", repo_dir)
+        .arg("hooks")
+        .arg("install")
+        .assert();
+    assert.failure().stderr(predicate::str::contains(
+        "no checklist for the pre-commit hook",
+    ));
+
+    fs::write(
+        repo_dir.join("kneeboard.toml"),
+        "[profiles.commit-msg]\nchecklist = 'test5.md'\n",
+    )
+    .unwrap();
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    cmd.current_dir(repo_dir)
+        .arg("hooks")
+        .arg("install")
+        .arg("commit-msg")
+        .assert()
+        .success();
+    let hook = fs::read_to_string(repo_dir.join("githooks").join("commit-msg")).unwrap();
+    assert!(hook.contains("exec kneeboard trailers --profile commit-msg \"$1\"\n"));
+
+    // Saving is opt-in, hooks that save start over on every commit
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    cmd.current_dir(repo_dir)
+        .arg("hooks")
+        .arg("install")
+        .arg("--save")
+        .arg("--checklist-path")
+        .arg("test5.md")
+        .arg("pre-commit")
+        .arg("commit-msg")
+        .assert()
+        .success();
+    let hook = fs::read_to_string(repo_dir.join("githooks").join("pre-commit")).unwrap();
+    assert!(
+        hook.contains(
+            "exec kneeboard --reset-rule on-new-commit --save --checklist-path test5.md\n"
+        )
+    );
+    let hook = fs::read_to_string(repo_dir.join("githooks").join("commit-msg")).unwrap();
+    assert!(hook.contains(
+        "exec kneeboard --reset-rule on-new-commit trailers --checklist-path test5.md \"$1\"\n"
+    ));
+}
+
+#[test]
+fn installed_hooks_add_trailers_to_commits() {
+    let checklist_path = copy_to_temp_dir("hook-trailers", "__test__/test_checklists/test4.md");
+    let repo_dir = checklist_path.parent().unwrap();
+    git_in(repo_dir, &["init", "-q"]);
+    fs::write(
+        repo_dir.join("checks.md"),
+        "<!-- checklist = 'pre-commit' -->\n- [ ] builds <!-- run: true -->\n- [ ] tests pass <!-- run: true -->\n<!-- checklist = 'pre-commit' -->\n",
+    )
+    .unwrap();
+    fs::write(
+        repo_dir.join("kneeboard.toml"),
+        "[profiles.pre-commit]\nchecklist = 'checks.md'\nheadless = true\n\n[profiles.commit-msg]\nchecklist = 'checks.md'\n",
+    )
+    .unwrap();
+
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    cmd.current_dir(repo_dir)
+        .args(["hooks", "install", "--save", "pre-commit", "commit-msg"])
+        .assert()
+        .success();
+
+    // The pre-commit hook saves the progress the commit-msg hook adds trailers for
+    git_in(
+        repo_dir,
+        &["commit", "-q", "--allow-empty", "-m", "feat: add things"],
+    );
+    let message = git_in(repo_dir, &["log", "-1", "--format=%B"]);
+    assert!(
+        message.contains("Checklist: pre-commit 2/2"),
+        "unexpected message: {message}"
+    );
+
+    // The next commit has to be checked again
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    cmd.current_dir(repo_dir)
+        .args([
+            "--reset-rule",
+            "on-new-commit",
+            "--save",
+            "--profile",
+            "pre-commit",
+        ])
+        .arg("--no-run")
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn trailers_and_verify_commit_commands() {
+    let checklist_path = copy_to_temp_dir("trailers", "__test__/test_checklists/test1.md");
+    let repo_dir = checklist_path.parent().unwrap();
+    git_in(repo_dir, &["init", "-q"]);
+    let message_path = repo_dir.join("message");
+
+    // Nothing saved yet, the message is left as it is
+    fs::write(&message_path, "feat: add things\n").unwrap();
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    cmd.current_dir(repo_dir)
+        .arg("trailers")
+        .arg("--checklist-path")
+        .arg("test1.md")
+        .arg(&message_path)
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(&message_path).unwrap(),
+        "feat: add things\n"
+    );
+
+    for item in [
+        "Test Checklist 1 normal item",
+        "Test Checklist 1 normal item 2",
+    ] {
+        let mut cmd = cargo_bin_cmd!("kneeboard");
+        cmd.current_dir(repo_dir)
+            .arg("check")
+            .arg("--checklist-path")
+            .arg("test1.md")
+            .arg("--item")
+            .arg(item)
+            .assert()
+            .success();
+    }
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    cmd.current_dir(repo_dir)
+        .arg("trailers")
+        .arg("--checklist-path")
+        .arg("test1.md")
+        .arg(&message_path)
+        .assert()
+        .success();
+    let message = fs::read_to_string(&message_path).unwrap();
+    assert!(
+        message.starts_with("feat: add things\n\nChecklist: Test Checklist 1 3/7\n"),
+        "unexpected message: {message}"
+    );
+    assert!(message.contains("Checklist-Skipped: Test Checklist 1 optional item [OPTIONAL]\n"));
+
+    git_in(
+        repo_dir,
+        &["commit", "-q", "--allow-empty", "--file", "message"],
+    );
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd
+        .current_dir(repo_dir)
+        .arg("verify-commit")
+        .arg("--checklist-path")
+        .arg("test1.md")
+        .assert();
+    assert
+        .failure()
+        .stdout(predicate::str::contains(
+            "HEAD: Test Checklist 1 3/7, 4 required items unresolved",
+        ))
+        .stdout(predicate::str::contains(
+            "HEAD: skipped \"Test Checklist 1 optional item [OPTIONAL]\"",
+        ));
+
+    fs::write(
+        &message_path,
+        "fix: done\n\nChecklist: Test Checklist 1 5/5\n",
+    )
+    .unwrap();
+    git_in(
+        repo_dir,
+        &["commit", "-q", "--allow-empty", "--file", "message"],
+    );
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    let assert = cmd.current_dir(repo_dir).arg("verify-commit").assert();
+    assert
+        .success()
+        .stdout("HEAD: Test Checklist 1 5/5 complete\n");
+
+    // Hyphenated names make it into the trailer as they are written
+    fs::copy(
+        "__test__/test_checklists/test4.md",
+        repo_dir.join("test4.md"),
+    )
+    .unwrap();
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    cmd.current_dir(repo_dir)
+        .args([
+            "check",
+            "--checklist-path",
+            "test4.md",
+            "--name",
+            "pre-commit",
+        ])
+        .args(["--item", "Test Checklist 4 pre-commit item"])
+        .args(["--item", "Test Checklist 4 pre-commit item 2"])
+        .assert()
+        .success();
+    fs::write(&message_path, "chore: tidy\n").unwrap();
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    cmd.current_dir(repo_dir)
+        .args([
+            "trailers",
+            "--checklist-path",
+            "test4.md",
+            "--name",
+            "pre-commit",
+        ])
+        .arg(&message_path)
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(&message_path).unwrap(),
+        "chore: tidy\n\nChecklist: pre-commit 2/2\n"
+    );
+    git_in(
+        repo_dir,
+        &["commit", "-q", "--allow-empty", "--file", "message"],
+    );
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    cmd.current_dir(repo_dir)
+        .args([
+            "verify-commit",
+            "--checklist-path",
+            "test4.md",
+            "--name",
+            "pre-commit",
+        ])
+        .assert()
+        .success()
+        .stdout("HEAD: pre-commit 2/2 complete\n");
+
+    // The blank first line of a prepare-commit-msg template is kept for the subject, with a
+    // blank line below it so the trailer stays a paragraph of its own
+    fs::write(
+        &message_path,
+        "\n# Please enter the commit message for your changes.\n",
+    )
+    .unwrap();
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    cmd.current_dir(repo_dir)
+        .args([
+            "trailers",
+            "--checklist-path",
+            "test4.md",
+            "--name",
+            "pre-commit",
+        ])
+        .arg(&message_path)
+        .assert()
+        .success();
+    let message = fs::read_to_string(&message_path).unwrap();
+    assert_eq!(
+        message,
+        "\n\nChecklist: pre-commit 2/2\n# Please enter the commit message for your changes.\n"
+    );
+    fs::write(&message_path, format!("chore: subject{message}")).unwrap();
+    git_in(
+        repo_dir,
+        &[
+            "commit",
+            "-q",
+            "--allow-empty",
+            "--cleanup=strip",
+            "--file",
+            "message",
+        ],
+    );
+    let mut cmd = cargo_bin_cmd!("kneeboard");
+    cmd.current_dir(repo_dir)
+        .args(["verify-commit", "--name", "pre-commit"])
+        .assert()
+        .success()
+        .stdout("HEAD: pre-commit 2/2 complete\n");
+}
+
+#[test]
+fn saving_to_git_notes() {
+    let checklist_path = copy_to_temp_dir("notes", "__test__/test_checklists/test5.md");
+    let repo_dir = checklist_path.parent().unwrap();
+    let status = |state: &str| {
+        let mut cmd = cargo_bin_cmd!("kneeboard");
+        cmd.current_dir(repo_dir)
+            .arg("status")
+            .arg("--state")
+            .arg(state)
+            .arg("--checklist-path")
+            .arg("test5.md")
+            .assert()
+    };
+    git_in(repo_dir, &["init", "-q", "-b", "main"]);
+    // Saving a note commits to the notes ref, which needs an identity
+    git_in(repo_dir, &["config", "user.name", "test"]);
+    git_in(repo_dir, &["config", "user.email", "test@example.com"]);
+    git_in(repo_dir, &["commit", "-q", "--allow-empty", "-m", "first"]);
+
+    for state in ["notes", "notes-branch"] {
+        let mut cmd = cargo_bin_cmd!("kneeboard");
+        cmd.current_dir(repo_dir)
+            .arg("check")
+            .arg("--state")
+            .arg(state)
+            .arg("--checklist-path")
+            .arg("test5.md")
+            .arg("--item")
+            .arg("normal item")
+            .assert()
+            .success();
+        status(state).code(2);
+    }
+    assert!(save_files_in(repo_dir).is_empty());
+    let note = git_in(
+        repo_dir,
+        &["notes", "--ref", "refs/notes/kneeboard", "show", "HEAD"],
+    );
+    assert!(note.contains("[progress.\"test5.md:Test Checklist 5\"]"));
+    assert!(note.contains("[[history.\"test5.md:Test Checklist 5\"]]"));
+
+    // Progress by commit starts over on a new commit, progress by branch carries on
+    git_in(repo_dir, &["commit", "-q", "--allow-empty", "-m", "second"]);
+    status("notes").code(3);
+    status("notes-branch").code(2);
+
+    git_in(repo_dir, &["checkout", "-q", "-b", "feature"]);
+    status("notes-branch")
+        .code(3)
+        .stdout(predicate::str::contains("No progress saved"));
+}
+
+#[test]
+fn saving_by_branch_and_collecting_deleted_branches() {
+    let checklist_path = copy_to_temp_dir("scope", "__test__/test_checklists/test5.md");
+    let repo_dir = checklist_path.parent().unwrap();
+    let kneeboard = |args: &[&str]| {
+        let mut cmd = cargo_bin_cmd!("kneeboard");
+        cmd.current_dir(repo_dir)
+            .args(args)
+            .args(["--scope", "branch", "--checklist-path", "test5.md"])
+            .assert()
+    };
+    let check = |state: &str| {
+        kneeboard(&["check", "--state", state, "--item", "normal item"]).success();
+    };
+    git_in(repo_dir, &["init", "-q", "-b", "main"]);
+    git_in(repo_dir, &["config", "user.name", "test"]);
+    git_in(repo_dir, &["config", "user.email", "test@example.com"]);
+    git_in(repo_dir, &["commit", "-q", "--allow-empty", "-m", "first"]);
+
+    check("checklist");
+    kneeboard(&["status"]).code(2);
+
+    // Every branch keeps progress of its own
+    git_in(repo_dir, &["checkout", "-q", "-b", "feature"]);
+    kneeboard(&["status"])
+        .code(3)
+        .stdout(predicate::str::contains("No progress saved"));
+    check("checklist");
+    assert_eq!(save_files_in(repo_dir).len(), 2);
+    git_in(repo_dir, &["checkout", "-q", "main"]);
+    kneeboard(&["status"]).code(2);
+
+    git_in(repo_dir, &["branch", "-q", "-D", "feature"]);
+    kneeboard(&["gc"])
+        .success()
+        .stdout("Removed the saved progress of branch feature\n");
+    assert_eq!(save_files_in(repo_dir).len(), 1);
+    kneeboard(&["gc"]).success().stdout("Nothing to collect\n");
+
+    // Notes are kept by branch with notes-branch rather than a scope
+    kneeboard(&["status", "--state", "notes"])
+        .failure()
+        .stderr(predicate::str::contains("use notes-branch"));
+    git_in(repo_dir, &["checkout", "-q", "-b", "notes"]);
+    let notes_branch = |args: &[&str]| {
+        let mut cmd = cargo_bin_cmd!("kneeboard");
+        cmd.current_dir(repo_dir)
+            .args(args)
+            .args(["--state", "notes-branch", "--checklist-path", "test5.md"])
+            .assert()
+    };
+    notes_branch(&["check", "--item", "normal item"]).success();
+    git_in(repo_dir, &["checkout", "-q", "main"]);
+    git_in(repo_dir, &["branch", "-q", "-D", "notes"]);
+    notes_branch(&["gc"])
+        .success()
+        .stdout("Removed the saved progress of branch notes\n");
 }